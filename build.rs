@@ -0,0 +1,46 @@
+use std::env;
+use std::fs;
+use std::path::Path;
+
+/// Turn every `inputs/examples/dayNN_NAME.txt` with a `dayNN_NAME.partK` answer next to it into a test
+fn main() {
+    let manifest_dir = env::var("CARGO_MANIFEST_DIR").unwrap();
+    let examples_dir = Path::new(&manifest_dir).join("inputs").join("examples");
+    println!("cargo:rerun-if-changed={}", examples_dir.display());
+
+    let mut names = fs::read_dir(&examples_dir)
+        .map(|entries| {
+            entries
+                .map(|entry| entry.unwrap().path())
+                .filter(|path| path.extension().is_some_and(|ext| ext == "txt"))
+                .map(|path| path.file_stem().unwrap().to_string_lossy().into_owned())
+                .collect::<Vec<_>>()
+        })
+        .unwrap_or_default();
+    names.sort();
+
+    let mut tests = String::new();
+    for name in names {
+        let Some(day) = name.strip_prefix("day").and_then(|rest| rest.get(..2)).and_then(|n| n.parse::<u32>().ok()) else {
+            panic!("Example {} should be named dayNN_NAME.txt", name);
+        };
+        let input = examples_dir.join(format!("{}.txt", name));
+        for part in 1..=2 {
+            let answer = examples_dir.join(format!("{}.part{}", name, part));
+            if !answer.exists() {
+                continue;
+            }
+            let test_name = name
+                .chars()
+                .map(|c| if c.is_ascii_alphanumeric() { c.to_ascii_lowercase() } else { '_' })
+                .collect::<String>();
+            tests += &format!(
+                "#[test]\nfn {}_part{}() {{\n    check({}, {}, include_str!({:?}), include_str!({:?}));\n}}\n\n",
+                test_name, part, day, part, input.display().to_string(), answer.display().to_string()
+            );
+        }
+    }
+
+    let out = Path::new(&env::var("OUT_DIR").unwrap()).join("examples.rs");
+    fs::write(out, tests).unwrap();
+}
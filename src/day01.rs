@@ -1,27 +1,150 @@
-pub fn part1(input: String) {
-    let mut prev = u16::MAX;
-    let mut count = 0;
-    for value in input.trim().split_whitespace().map(|s| s.parse().unwrap()) {
-        if value > prev {
-            count += 1
+use std::collections::VecDeque;
+use std::io::BufRead;
+use std::ops::Sub;
+
+/// What a sonar sweep found, comparing each window of readings against the one before it
+#[derive(Debug, PartialEq)]
+pub struct SonarReport<T> {
+    /// How many windows had a larger sum than the previous window
+    pub increases: usize,
+    /// The most windows in a row that each increased on the one before
+    pub longest_run: usize,
+    /// The largest increase in sum from one window to the next
+    pub largest_jump: Option<T>,
+}
+
+/// Sliding-window analysis over a stream of depth readings, one reading at a time.
+///
+/// Consecutive windows share all but one reading, so comparing their sums only needs the reading
+/// that entered and the one that left. Nothing is ever summed in `T`, so any width of integer works.
+pub struct Sonar<T> {
+    window: usize,
+    readings: VecDeque<T>,
+    sum: i128,
+    run: usize,
+    report: SonarReport<T>,
+}
+
+impl<T> Sonar<T>
+where
+    T: Copy + Ord + Sub<Output = T> + Into<i128>,
+{
+    pub fn new(window: usize) -> Sonar<T> {
+        assert!(window > 0, "Window must hold at least one reading");
+        Sonar {
+            window,
+            readings: VecDeque::with_capacity(window + 1),
+            sum: 0,
+            run: 0,
+            report: SonarReport { increases: 0, longest_run: 0, largest_jump: None },
         }
-        prev = value;
     }
-    println!("{}", count);
-}
 
-pub fn part2(input: String) {
-    let mut a = u32::MAX / 3;
-    let mut b = u32::MAX / 3;
-    let mut c = u32::MAX / 3;
-    let mut count = 0;
-    for value in input.trim().split_whitespace().map(|s| s.parse().unwrap()) {
-        if a + b + c < b + c + value {
-            count += 1
+    pub fn push(&mut self, depth: T) {
+        self.readings.push_back(depth);
+        self.sum += depth.into();
+        if self.readings.len() > self.window {
+            let dropped = self.readings.pop_front().unwrap();
+            self.sum -= dropped.into();
+            if depth > dropped {
+                let jump = depth - dropped;
+                self.report.increases += 1;
+                self.run += 1;
+                self.report.longest_run = self.report.longest_run.max(self.run);
+                self.report.largest_jump = self.report.largest_jump.max(Some(jump));
+            } else {
+                self.run = 0;
+            }
         }
-        a = b;
-        b = c;
-        c = value;
     }
-    println!("{}", count);
-}
\ No newline at end of file
+
+    /// The mean of the current window, once it has filled up
+    pub fn average(&self) -> Option<f64> {
+        (self.readings.len() == self.window).then(|| self.sum as f64 / self.window as f64)
+    }
+
+    pub fn report(self) -> SonarReport<T> {
+        self.report
+    }
+}
+
+pub fn analyze<T>(depths: impl IntoIterator<Item = T>, window: usize) -> SonarReport<T>
+where
+    T: Copy + Ord + Sub<Output = T> + Into<i128>,
+{
+    let mut sonar = Sonar::new(window);
+    for depth in depths {
+        sonar.push(depth);
+    }
+    sonar.report()
+}
+
+/// The mean of every full window, in order
+pub fn moving_average<T>(depths: impl IntoIterator<Item = T>, window: usize) -> impl Iterator<Item = f64>
+where
+    T: Copy + Ord + Sub<Output = T> + Into<i128>,
+{
+    depths.into_iter()
+        .scan(Sonar::new(window), |sonar, depth| {
+            sonar.push(depth);
+            Some(sonar.average())
+        })
+        .flatten()
+}
+
+fn depths(input: &mut dyn BufRead) -> impl Iterator<Item = u64> + '_ {
+    input.lines()
+        .map(|line| line.unwrap())
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| line.trim().parse().unwrap())
+}
+
+pub fn part1(input: &mut dyn BufRead) -> String {
+    analyze(depths(input), 1).increases.to_string()
+}
+
+pub fn part2(input: &mut dyn BufRead) -> String {
+    analyze(depths(input), 3).increases.to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn report() {
+        let report = analyze([199u16, 200, 208, 210, 200, 207, 240, 269, 260, 263], 3);
+        assert_eq!(report.increases, 5);
+        assert_eq!(report.longest_run, 4);
+        assert_eq!(report.largest_jump, Some(69));
+    }
+
+    #[test]
+    fn average() {
+        let depths = [199u16, 200, 208, 210, 200, 207, 240, 269, 260, 263];
+        assert_eq!(moving_average(depths, 3).collect::<Vec<_>>(), vec![
+            607.0 / 3.0, 618.0 / 3.0, 618.0 / 3.0, 617.0 / 3.0, 647.0 / 3.0, 716.0 / 3.0, 769.0 / 3.0, 792.0 / 3.0,
+        ]);
+    }
+
+    #[test]
+    fn wide_readings() {
+        let report = analyze([u64::MAX - 2, u64::MAX - 1, u64::MAX, 0, u64::MAX], 2);
+        assert_eq!(report.increases, 1);
+        assert_eq!(report.largest_jump, Some(2));
+        assert_eq!(moving_average([u64::MAX - 2, u64::MAX - 1], 2).next(), Some((u64::MAX as f64) - 1.5));
+    }
+
+    #[test]
+    fn blank_lines() {
+        let input = "199\n200\n\n208\n   \n210\n\n";
+        assert_eq!(part1(&mut input.as_bytes()), "3");
+    }
+
+    #[test]
+    fn window_longer_than_input() {
+        let report = analyze([1i32, 2, 3], 4);
+        assert_eq!(report, SonarReport { increases: 0, longest_run: 0, largest_jump: None });
+        assert_eq!(moving_average([1i32, 2, 3], 4).next(), None);
+    }
+}
@@ -1,43 +1,191 @@
-pub fn part1(input: String) {
-    let instructions: Vec<&str> = input.lines().collect();
+use std::fmt;
+use std::io::{BufRead, Write};
+use std::str::FromStr;
 
-    let mut pos = 0;
-    let mut depth = 0;
-
-    for inst in instructions {
-        let (dir, dist) = inst.split_once(' ').unwrap();
-        let dist: i32 = dist.parse().unwrap();
+#[derive(Clone, Debug, PartialEq)]
+pub enum Command {
+    Forward(i64),
+    Back(i64),
+    Down(i64),
+    Up(i64),
+    /// Rise straight up to a depth of 0
+    Surface,
+    /// `repeat N { ... }`: run the block N times
+    Repeat(usize, Vec<Command>),
+}
 
-        match dir {
-            "down" => depth += dist,
-            "up" => depth -= dist,
-            "forward" => pos += dist,
-            _ => panic!()
+impl Command {
+    /// Parse the command starting with `word`, taking any arguments and block contents from `words`
+    fn parse(word: &str, words: &mut impl Iterator<Item = String>) -> Command {
+        fn number<T: FromStr>(word: &str, words: &mut impl Iterator<Item = String>) -> T {
+            let arg = words.next().unwrap_or_else(|| panic!("Missing argument for {}", word));
+            arg.parse().unwrap_or_else(|_| panic!("Invalid argument for {}: {}", word, arg))
+        }
+        match word {
+            "forward" => Command::Forward(number(word, words)),
+            "back" => Command::Back(number(word, words)),
+            "down" => Command::Down(number(word, words)),
+            "up" => Command::Up(number(word, words)),
+            "surface" => Command::Surface,
+            "repeat" => {
+                let times: usize = number(word, words);
+                assert_eq!(words.next().as_deref(), Some("{"), "Expected a block after repeat {}", times);
+                let mut block = vec![];
+                loop {
+                    match words.next().as_deref() {
+                        Some("}") => break,
+                        Some(word) => block.push(Command::parse(word, words)),
+                        None => panic!("Unclosed repeat block"),
+                    }
+                }
+                Command::Repeat(times, block)
+            }
+            _ => panic!("Unknown command {}", word),
         }
     }
-    println!("{}", pos * depth);
 }
 
-pub fn part2(input: String) {
-    let instructions: Vec<&str> = input.lines().collect();
+/// Parse commands one at a time as the input is read.
+///
+/// Words are whitespace-separated, so a `repeat` block can sit on one line or span several.
+pub fn commands(input: &mut dyn BufRead) -> impl Iterator<Item = Command> + '_ {
+    let mut words = input.lines().flat_map(|line| {
+        line.unwrap().split_whitespace().map(String::from).collect::<Vec<_>>()
+    });
+    std::iter::from_fn(move || {
+        let word = words.next()?;
+        Some(Command::parse(&word, &mut words))
+    })
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct State {
+    pub position: i64,
+    pub depth: i64,
+    pub aim: i64,
+}
+
+impl fmt::Display for State {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{},{},{}", self.position, self.depth, self.aim)
+    }
+}
+
+/// A movement model: how each command changes the submarine's state
+pub trait Submarine {
+    fn forward(&mut self, x: i64);
+    fn down(&mut self, x: i64);
+    fn surface(&mut self);
+    fn state(&self) -> State;
+
+    fn back(&mut self, x: i64) { self.forward(-x) }
+    fn up(&mut self, x: i64) { self.down(-x) }
+}
 
-    let mut pos = 0;
-    let mut depth = 0;
-    let mut aim = 0;
+/// `down` and `up` change depth directly
+#[derive(Default)]
+pub struct Direct(State);
+
+impl Submarine for Direct {
+    fn forward(&mut self, x: i64) { self.0.position += x }
+    fn down(&mut self, x: i64) { self.0.depth += x }
+    fn surface(&mut self) { self.0.depth = 0 }
+    fn state(&self) -> State { self.0 }
+}
 
-    for inst in instructions {
-        let (dir, x) = inst.split_once(' ').unwrap();
-        let x: i32 = x.parse().unwrap();
+/// `down` and `up` change aim, and moving forward changes depth by aim times the distance
+#[derive(Default)]
+pub struct Aimed(State);
 
-        match dir {
-            "down" => aim += x,
-            "up" => aim -= x,
-            "forward" => {
-                pos += x;
-                depth += aim * x;
-            },
-            _ => panic!()
+impl Submarine for Aimed {
+    fn forward(&mut self, x: i64) {
+        self.0.position += x;
+        self.0.depth += self.0.aim * x;
+    }
+    fn down(&mut self, x: i64) { self.0.aim += x }
+    /// Surfacing keeps the current aim
+    fn surface(&mut self) { self.0.depth = 0 }
+    fn state(&self) -> State { self.0 }
+}
+
+fn execute(sub: &mut dyn Submarine, command: &Command, trace: &mut Option<&mut dyn Write>) {
+    match command {
+        Command::Forward(x) => sub.forward(*x),
+        Command::Back(x) => sub.back(*x),
+        Command::Down(x) => sub.down(*x),
+        Command::Up(x) => sub.up(*x),
+        Command::Surface => sub.surface(),
+        Command::Repeat(times, block) => {
+            for _ in 0..*times {
+                for command in block {
+                    execute(sub, command, trace);
+                }
+            }
+            return;
         }
     }
-    println!("{}", pos * depth);
-}
\ No newline at end of file
+    if let Some(out) = trace {
+        writeln!(out, "{}", sub.state()).unwrap();
+    }
+}
+
+/// Steer `sub` through every command in `input`.
+///
+/// In trace mode, the state after each movement is written to `trace` as `position,depth,aim` CSV, starting
+/// with a header and the initial state, for plotting the route.
+pub fn run(input: &mut dyn BufRead, sub: &mut dyn Submarine, mut trace: Option<&mut dyn Write>) {
+    if let Some(out) = &mut trace {
+        writeln!(out, "position,depth,aim\n{}", sub.state()).unwrap();
+    }
+    for command in commands(input) {
+        execute(sub, &command, &mut trace);
+    }
+}
+
+fn solve(input: &mut dyn BufRead, mut sub: impl Submarine) -> String {
+    run(input, &mut sub, None);
+    let State { position, depth, .. } = sub.state();
+    (position * depth).to_string()
+}
+
+pub fn part1(input: &mut dyn BufRead) -> String { solve(input, Direct::default()) }
+
+pub fn part2(input: &mut dyn BufRead) -> String { solve(input, Aimed::default()) }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_repeat() {
+        let mut input = "down 2\nrepeat 2 { forward 1\n  repeat 3 {\n    up 1\n  }\n}\nsurface\nback 4".as_bytes();
+        assert_eq!(commands(&mut input).collect::<Vec<_>>(), vec![
+            Command::Down(2),
+            Command::Repeat(2, vec![Command::Forward(1), Command::Repeat(3, vec![Command::Up(1)])]),
+            Command::Surface,
+            Command::Back(4),
+        ]);
+    }
+
+    #[test]
+    #[should_panic(expected = "Invalid argument for repeat: -1")]
+    fn negative_repeat() {
+        commands(&mut "repeat -1 { forward 1 }".as_bytes()).for_each(drop);
+    }
+
+    #[test]
+    fn trace() {
+        let mut input = "down 5\nrepeat 2 {\nforward 3\n}\nsurface\nback 1".as_bytes();
+        let mut out = vec![];
+        let mut sub = Aimed::default();
+        run(&mut input, &mut sub, Some(&mut out));
+        assert_eq!(String::from_utf8(out).unwrap(), "position,depth,aim
+0,0,0
+0,0,5
+3,15,5
+6,30,5
+6,0,5
+5,-5,5
+");
+    }
+}
@@ -1,56 +1,172 @@
-use std::collections::HashMap;
+/// Which bit wins when a column has as many 0s as 1s
+#[derive(Clone, Copy, Debug)]
+pub enum TieBreak {
+    Zero,
+    One,
+}
 
-fn common_value(items: &Vec<&str>, pos: usize) -> char {
-    let mut chars = HashMap::new();
+/// Which bit to pick out of a column
+#[derive(Clone, Copy, Debug)]
+pub enum Criterion {
+    MostCommon(TieBreak),
+    LeastCommon(TieBreak),
+}
 
-    for i in items {
-        let ch = i.chars().nth(pos).unwrap();
-        *chars.entry(ch).or_insert(0) += 1;
+impl Criterion {
+    fn pick(self, zeros: usize, ones: usize) -> u128 {
+        // A bit missing from the column can't be the least common one
+        if zeros == 0 || ones == 0 {
+            return (ones > 0) as u128;
+        }
+        let (prefer_one, tie) = match self {
+            Criterion::MostCommon(tie) => (ones > zeros, tie),
+            Criterion::LeastCommon(tie) => (ones < zeros, tie),
+        };
+        if zeros == ones {
+            return matches!(tie, TieBreak::One) as u128;
+        }
+        prefer_one as u128
     }
-    chars.into_iter()
-        .max_by_key(|&(_, count)| count)
-        .map(|(value, _)| value)
-        .unwrap()
 }
 
-pub fn part1(input: String) {
-    let instructions: Vec<&str> = input.lines().collect();
-    let n_bits = instructions[0].len();
+/// How `Report::filter` narrows down the candidates
+#[derive(Clone, Copy, Debug)]
+pub enum Strategy {
+    /// Recount the remaining candidates at every column
+    Scan,
+    /// Sort once so every prefix is a contiguous range, like walking a trie, and split ranges by binary search
+    SortedTrie,
+}
 
-    let mut gamma = 0;
-    let mut epsilon = 0;
+/// A diagnostic report packed into integers, up to 128 bits wide.
+///
+/// Column 0 is the leftmost, most significant bit.
+pub struct Report {
+    width: usize,
+    values: Vec<u128>,
+    /// How many values have a 1 in each column
+    ones: Vec<usize>,
+}
 
-    for i in 0..n_bits {
-        gamma *= 2;
-        epsilon *= 2;
-        match common_value(&instructions, i) {
-            '1' => gamma += 1,
-            '0' => epsilon += 1,
-            _ => panic!(),
-        };
+impl Report {
+    pub fn parse(input: &str) -> Report {
+        let width = input.lines().next().map_or(0, str::len);
+        assert!(width <= 128, "Reports wider than 128 bits are not supported");
+        let mut ones = vec![0; width];
+        let values = input.lines()
+            .map(|line| {
+                assert_eq!(line.len(), width, "All lines of the report must be the same width");
+                let value = u128::from_str_radix(line, 2).unwrap();
+                for (col, count) in ones.iter_mut().enumerate() {
+                    *count += bit(value, width, col) as usize;
+                }
+                value
+            })
+            .collect();
+        Report { width, values, ones }
+    }
+
+    /// The value made of the bit picked from each column across the whole report
+    pub fn column_value(&self, criterion: Criterion) -> u128 {
+        self.ones.iter().fold(0, |value, &ones| {
+            value << 1 | criterion.pick(self.values.len() - ones, ones)
+        })
+    }
+
+    /// Keep the values whose bit matches the criterion, one column at a time, until only one is left
+    pub fn filter(&self, criterion: Criterion, strategy: Strategy) -> Option<u128> {
+        match strategy {
+            Strategy::Scan => {
+                let mut candidates = self.values.clone();
+                for col in 0..self.width {
+                    if candidates.len() <= 1 { break; }
+                    let ones = candidates.iter().filter(|&&v| bit(v, self.width, col) == 1).count();
+                    let keep = criterion.pick(candidates.len() - ones, ones);
+                    candidates.retain(|&v| bit(v, self.width, col) == keep);
+                }
+                candidates.first().copied()
+            }
+            Strategy::SortedTrie => {
+                let mut sorted = self.values.clone();
+                sorted.sort_unstable();
+                let (mut lo, mut hi) = (0, sorted.len());
+                for col in 0..self.width {
+                    if hi - lo <= 1 { break; }
+                    // Everything in range shares the bits before `col`, so the 0s all come before the 1s
+                    let split = lo + sorted[lo..hi].partition_point(|&v| bit(v, self.width, col) == 0);
+                    match criterion.pick(split - lo, hi - split) {
+                        0 => hi = split,
+                        _ => lo = split,
+                    }
+                }
+                sorted.get(lo).filter(|_| lo < hi).copied()
+            }
+        }
+    }
+
+    /// Flip every bit within the report's width
+    fn complement(&self, value: u128) -> u128 {
+        let mask = if self.width == 128 { u128::MAX } else { (1 << self.width) - 1 };
+        !value & mask
     }
+}
+
+fn bit(value: u128, width: usize, col: usize) -> u128 {
+    (value >> (width - 1 - col)) & 1
+}
 
-    println!("{}", gamma * epsilon);
+fn product(a: u128, b: u128) -> String {
+    a.checked_mul(b).expect("Answer does not fit in 128 bits").to_string()
 }
 
-pub fn part2(input: String) {
-    let instructions: Vec<&str> = input.lines().collect();
+pub fn part1(input: &str) -> String {
+    let report = Report::parse(input);
+    let gamma = report.column_value(Criterion::MostCommon(TieBreak::One));
+    product(gamma, report.complement(gamma))
+}
 
-    let mut candidates = instructions.clone();
-    for pos in 0..instructions[0].len() {
-        let filter_value = common_value(&candidates, pos);
-        candidates.retain(|x| x.chars().nth(pos).unwrap() == filter_value);
-        if candidates.len() == 1 { break; };
+pub fn part2(input: &str) -> String {
+    let report = Report::parse(input);
+    let o2_rating = report.filter(Criterion::MostCommon(TieBreak::One), Strategy::SortedTrie).unwrap();
+    let co2_rating = report.filter(Criterion::LeastCommon(TieBreak::Zero), Strategy::SortedTrie).unwrap();
+    product(o2_rating, co2_rating)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SAMPLE: &str = include_str!("../inputs/examples/day03_sample.txt");
+
+    #[test]
+    fn strategies_agree() {
+        let report = Report::parse(SAMPLE);
+        for criterion in [
+            Criterion::MostCommon(TieBreak::One),
+            Criterion::MostCommon(TieBreak::Zero),
+            Criterion::LeastCommon(TieBreak::One),
+            Criterion::LeastCommon(TieBreak::Zero),
+        ] {
+            assert_eq!(report.filter(criterion, Strategy::Scan), report.filter(criterion, Strategy::SortedTrie));
+        }
     }
-    let o2_rating = i32::from_str_radix(candidates.pop().unwrap(), 2).unwrap();
 
-    candidates = instructions.clone();
-    for pos in 0..instructions[0].len() {
-        let filter_value = common_value(&candidates, pos);
-        candidates.retain(|x| x.chars().nth(pos).unwrap() != filter_value);
-        if candidates.len() == 1 { break; };
+    #[test]
+    fn ties() {
+        let report = Report::parse("10\n01\n11\n00");
+        assert_eq!(report.column_value(Criterion::MostCommon(TieBreak::One)), 0b11);
+        assert_eq!(report.column_value(Criterion::MostCommon(TieBreak::Zero)), 0b00);
+        assert_eq!(report.filter(Criterion::LeastCommon(TieBreak::One), Strategy::Scan), Some(0b11));
+        assert_eq!(report.filter(Criterion::LeastCommon(TieBreak::Zero), Strategy::SortedTrie), Some(0b00));
     }
-    let co2_rating = i32::from_str_radix(candidates.pop().unwrap(), 2).unwrap();
 
-    println!("{}", o2_rating * co2_rating);
-}
\ No newline at end of file
+    #[test]
+    fn wide_report() {
+        let ones = "1".repeat(100);
+        let mixed = format!("{}{}", "0".repeat(50), "1".repeat(50));
+        let report = Report::parse(&format!("{}\n{}\n{}", ones, ones, mixed));
+        assert_eq!(report.column_value(Criterion::MostCommon(TieBreak::One)), (1 << 100) - 1);
+        assert_eq!(report.filter(Criterion::LeastCommon(TieBreak::Zero), Strategy::Scan), Some((1 << 50) - 1));
+        assert_eq!(report.filter(Criterion::LeastCommon(TieBreak::Zero), Strategy::SortedTrie), Some((1 << 50) - 1));
+    }
+}
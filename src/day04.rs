@@ -1,112 +1,234 @@
-use std::collections::HashSet;
+use std::collections::HashMap;
 use std::fmt;
 
+use tracing::{debug, trace};
+
+/// A set of cells that wins a board once every one of them is marked
+#[derive(Clone, Copy, Debug)]
+pub enum Pattern {
+    Row,
+    Column,
+    /// Either corner-to-corner diagonal; only square boards have these
+    Diagonal,
+    FourCorners,
+    Blackout,
+}
+
 #[derive(Debug)]
-#[derive(Eq, PartialEq, Hash)]
-struct Board {
-    nums: [[i32; 5]; 5],
+pub struct Board {
+    width: usize,
+    height: usize,
+    /// Numbers in row-major order
+    nums: Vec<i32>,
+    marked: Vec<bool>,
+    row_marks: Vec<usize>,
+    col_marks: Vec<usize>,
+    diagonal_marks: [usize; 2],
+    /// The distinct corner cells, which are fewer than four on a board one cell wide or high
+    corners: Vec<usize>,
+    corner_marks: usize,
+    total_marks: usize,
+    unmarked_sum: i64,
 }
 
 impl fmt::Display for Board {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        let rows = self.nums.map(|row| {
-            format!("{:>2} {:>2} {:>2} {:>2} {:>2}", row[0], row[1], row[2], row[3], row[4])
-        });
-        write!(f, "{}\n{}\n{}\n{}\n{}", rows[0], rows[1], rows[2], rows[3], rows[4])
+        let rows = self.nums.chunks(self.width)
+            .map(|row| row.iter().map(|n| format!("{:>2}", n)).collect::<Vec<_>>().join(" "))
+            .collect::<Vec<_>>();
+        write!(f, "{}", rows.join("\n"))
     }
 }
 
 impl Board {
     fn from_str(s: &str) -> Board {
-        let mut nums = [[0; 5]; 5];
-        for (i, row) in s.split("\n").enumerate() {
-            // println!("Row {} is {:?}", i, row);
-            for (j, num) in row.split_whitespace().map(|n| n.parse::<i32>().unwrap()).enumerate() {
-                nums[i][j] = num;
-                // println!("{} -> {}", j, num);
+        let mut nums = vec![];
+        let mut width = 0;
+        let mut height = 0;
+        for (i, row) in s.lines().filter(|row| !row.trim().is_empty()).enumerate() {
+            trace!(row = i, text = row, "Parsing board row");
+            let row = row.split_whitespace().map(|n| n.parse::<i32>().unwrap()).collect::<Vec<_>>();
+            if i == 0 {
+                width = row.len();
             }
+            assert_eq!(row.len(), width, "Every row of a board must be the same width");
+            nums.extend(row);
+            height += 1;
+        }
+        let mut corners = vec![0, width - 1, nums.len() - width, nums.len() - 1];
+        corners.sort();
+        corners.dedup();
+        Board {
+            width,
+            height,
+            marked: vec![false; nums.len()],
+            row_marks: vec![0; height],
+            col_marks: vec![0; width],
+            diagonal_marks: [0, 0],
+            corners,
+            corner_marks: 0,
+            total_marks: 0,
+            unmarked_sum: nums.iter().map(|&n| n as i64).sum(),
+            nums,
         }
-        Board { nums }
     }
 
-    fn row(&self, idx: usize) -> [i32; 5] {
-        self.nums[idx]
-    }
-    fn column(&self, idx: usize) -> [i32; 5] {
-        let mut col = [0; 5];
-        for (i, row) in self.nums.iter().enumerate() {
-            col[i] = row[idx];
+    /// Mark one cell and report whether that completed any of `patterns`
+    fn mark(&mut self, cell: usize, patterns: &[Pattern]) -> bool {
+        if self.marked[cell] {
+            return false;
+        }
+        self.marked[cell] = true;
+        self.unmarked_sum -= self.nums[cell] as i64;
+        let (row, col) = (cell / self.width, cell % self.width);
+        self.row_marks[row] += 1;
+        self.col_marks[col] += 1;
+        self.total_marks += 1;
+        let square = self.width == self.height;
+        if square && row == col {
+            self.diagonal_marks[0] += 1;
+        }
+        if square && row + col == self.width - 1 {
+            self.diagonal_marks[1] += 1;
+        }
+        let corner = self.corners.contains(&cell);
+        if corner {
+            self.corner_marks += 1;
         }
-        col
-    }
 
-    fn check(&self, called_nums: &[i32]) -> bool {
-        for x in 0..5 {
-            if self.row(x).iter().all(|n| called_nums.contains(n)) {
-                return true;
+        patterns.iter().any(|pattern| match pattern {
+            Pattern::Row => self.row_marks[row] == self.width,
+            Pattern::Column => self.col_marks[col] == self.height,
+            Pattern::Diagonal => {
+                square && ((row == col && self.diagonal_marks[0] == self.width)
+                    || (row + col == self.width - 1 && self.diagonal_marks[1] == self.width))
             }
-            if self.column(x).iter().all(|n| called_nums.contains(n)) {
-                return true;
+            Pattern::FourCorners => corner && self.corner_marks == self.corners.len(),
+            Pattern::Blackout => self.total_marks == self.nums.len(),
+        })
+    }
+}
+
+/// A board completing a pattern
+#[derive(Debug, PartialEq)]
+pub struct Win {
+    pub board: usize,
+    /// Index into the draws of the number that completed the board
+    pub draw: usize,
+    pub score: i64,
+}
+
+pub struct Bingo {
+    boards: Vec<Board>,
+    patterns: Vec<Pattern>,
+    /// Where each number appears, as (board, cell)
+    index: HashMap<i32, Vec<(usize, usize)>>,
+}
+
+impl Bingo {
+    pub fn new(boards: Vec<Board>, patterns: &[Pattern]) -> Bingo {
+        let mut index: HashMap<i32, Vec<(usize, usize)>> = HashMap::new();
+        for (b, board) in boards.iter().enumerate() {
+            for (cell, &num) in board.nums.iter().enumerate() {
+                index.entry(num).or_default().push((b, cell));
             }
-        };
-        false
+        }
+        Bingo { boards, patterns: patterns.to_vec(), index }
     }
 
-    fn score(&self, called_nums: &[i32]) -> i32 {
-        let mut uncalled_sum = 0;
-        for row in self.nums {
-            for num in row {
-                if !called_nums.contains(&num) {
-                    uncalled_sum += num;
+    /// Call every number in turn, returning each board's win in the order they happen.
+    ///
+    /// Boards that win on the same draw are ordered by their position in the input. Boards that never win
+    /// are left out.
+    pub fn play(mut self, draws: &[i32]) -> Vec<Win> {
+        let mut won = vec![false; self.boards.len()];
+        let mut wins = vec![];
+        for (draw, &num) in draws.iter().enumerate() {
+            let mut winners = vec![];
+            for &(b, cell) in self.index.get(&num).into_iter().flatten() {
+                if !won[b] && self.boards[b].mark(cell, &self.patterns) {
+                    won[b] = true;
+                    winners.push(b);
                 }
             }
+            winners.sort();
+            for b in winners {
+                let score = self.boards[b].unmarked_sum * num as i64;
+                debug!(board = b, draw, num, score, "Board won");
+                wins.push(Win { board: b, draw, score });
+            }
         }
-        uncalled_sum * called_nums.last().unwrap()
+        wins
     }
 }
 
-
-fn parse_input(s: String) -> (Vec<i32>, Vec<Board>) {
+fn parse_input(s: &str) -> (Vec<i32>, Vec<Board>) {
     let mut inputs: std::str::Split<&str> = s.split("\n\n");
 
-    let nums: Vec<i32> = inputs.next().unwrap().split(",").map(|x| x.parse::<i32>().unwrap()).collect();
-    let boards: Vec<Board> = inputs.map(|s| Board::from_str(s)).collect();
+    let nums: Vec<i32> = inputs.next().unwrap().trim().split(",").map(|x| x.parse::<i32>().unwrap()).collect();
+    // Extra blank lines, such as at the end of the file, leave chunks with no board in them
+    let boards: Vec<Board> = inputs.filter(|chunk| !chunk.trim().is_empty()).map(Board::from_str).collect();
 
     (nums, boards)
 }
 
-
-pub fn part1(input: String) {
+fn play(input: &str) -> Vec<Win> {
     let (nums, boards) = parse_input(input);
+    Bingo::new(boards, &[Pattern::Row, Pattern::Column]).play(&nums)
+}
 
-    for i in 1..nums.len() {
-        let called_nums = &nums[0..i];
-        for board in &boards {
-            if board.check(called_nums) {
-                println!("{}", board.score(called_nums));
-                return;
-            }
-        }
-    }
+pub fn part1(input: &str) -> String {
+    play(input).first().expect("No board ever wins").score.to_string()
 }
 
-pub fn part2(input: String) {
-    let (nums, boards) = parse_input(input);
+pub fn part2(input: &str) -> String {
+    play(input).last().expect("No board ever wins").score.to_string()
+}
 
-    let mut last_score = 0;
-    let mut completed: HashSet<&Board> = HashSet::new();
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-    for i in 1..nums.len() {
-        let called_nums = &nums[0..i];
-        for board in &boards {
-            if completed.contains(board) {
-                continue
-            }
-            if board.check(called_nums) {
-                completed.insert(board);
-                last_score = board.score(called_nums);
-            }
-        }
+    fn wins(boards: &[&str], patterns: &[Pattern], draws: &[i32]) -> Vec<Win> {
+        let boards = boards.iter().map(|s| Board::from_str(s)).collect();
+        Bingo::new(boards, patterns).play(draws)
     }
-    println!("{}", last_score);
-}
\ No newline at end of file
+
+    #[test]
+    fn win_order() {
+        let (nums, boards) = parse_input(include_str!("../inputs/examples/day04_sample.txt"));
+        let wins = Bingo::new(boards, &[Pattern::Row, Pattern::Column]).play(&nums);
+        assert_eq!(wins.iter().map(|w| (w.board, w.draw)).collect::<Vec<_>>(), vec![(2, 11), (0, 13), (1, 14)]);
+        assert_eq!(wins[0].score, 4512);
+        assert_eq!(wins[2].score, 1924);
+    }
+
+    #[test]
+    fn patterns() {
+        let board = "1 2 3\n4 5 6\n7 8 9";
+        assert_eq!(wins(&[board], &[Pattern::Diagonal], &[3, 1, 5, 9]), vec![Win { board: 0, draw: 3, score: 9 * 27 }]);
+        assert_eq!(wins(&[board], &[Pattern::Diagonal], &[7, 5, 3]), vec![Win { board: 0, draw: 2, score: 3 * 30 }]);
+        assert_eq!(wins(&[board], &[Pattern::FourCorners], &[1, 5, 3, 7, 9]), vec![Win { board: 0, draw: 4, score: 9 * 20 }]);
+        assert_eq!(wins(&[board], &[Pattern::Blackout], &[1, 2, 3, 4, 5, 6, 7, 8]), vec![]);
+        assert_eq!(wins(&[board], &[Pattern::Row], &[1, 4, 7]), vec![]);
+    }
+
+    #[test]
+    fn rectangular_boards() {
+        let wide = "1 2 3 4\n5 6 7 8";
+        // Diagonals only exist on square boards
+        assert_eq!(wins(&[wide], &[Pattern::Diagonal], &[1, 6, 7, 4]), vec![]);
+        assert_eq!(wins(&[wide], &[Pattern::Column], &[2, 3, 7]), vec![Win { board: 0, draw: 2, score: 7 * 24 }]);
+        assert_eq!(wins(&[wide, "3\n7"], &[Pattern::Column], &[7, 3]), vec![
+            Win { board: 0, draw: 1, score: 3 * 26 },
+            Win { board: 1, draw: 1, score: 0 },
+        ]);
+    }
+
+    #[test]
+    fn extra_blank_lines() {
+        let (nums, boards) = parse_input("1,2\n\n1 2\n3 4\n\n\n\n3 4\n5 6\n\n");
+        assert_eq!(nums, vec![1, 2]);
+        assert_eq!(boards.len(), 2);
+    }
+}
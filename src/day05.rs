@@ -1,18 +1,24 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::fmt;
+use std::io::{BufRead, Write};
 
 use regex::Regex;
+use tracing::trace;
 
-type Point = (i32, i32);
+pub type Point = (i64, i64);
 
-fn sign(x: i32) -> i32 {
-    if x > 0 { 1 } else if x < 0 { -1 } else { 0 }
+fn gcd(a: i64, b: i64) -> i64 {
+    if b == 0 { a.abs() } else { gcd(b, a % b) }
 }
 
+/// The infinite line a segment lies on, as its normalised lattice step and an offset that is the same for
+/// every point on it
+type Carrier = (Point, i64);
+
 #[derive(Debug)]
-struct Line {
-    start: Point,
-    end: Point,
+pub struct Line {
+    pub start: Point,
+    pub end: Point,
 }
 
 impl fmt::Display for Line {
@@ -22,76 +28,362 @@ impl fmt::Display for Line {
 }
 
 impl Line {
-    fn is_diagonal(&self) -> bool {
+    pub fn is_diagonal(&self) -> bool {
         self.start.0 != self.end.0 && self.start.1 != self.end.1
     }
 
-    fn points(&self) -> Vec<Point> {
-        let dx = sign(self.end.0 - self.start.0);
-        let dy = sign(self.end.1 - self.start.1);
-        let mut points: Vec<Point> = vec![self.start];
-        let (mut x, mut y) = self.start;
-        while (x, y) != self.end {
-            x += dx;
-            y += dy;
-            points.push((x, y));
+    /// The smallest step between lattice points along the segment, and how many steps it takes to get
+    /// from start to end. A single point counts as horizontal.
+    pub fn step(&self) -> (Point, i64) {
+        let (dx, dy) = (self.end.0 - self.start.0, self.end.1 - self.start.1);
+        let n = gcd(dx, dy);
+        if n == 0 {
+            return ((1, 0), 0);
         }
-        points
+        ((dx / n, dy / n), n)
+    }
+
+    /// Every lattice point on the segment, from start to end
+    pub fn points(&self) -> impl Iterator<Item = Point> {
+        let ((sx, sy), n) = self.step();
+        let (x, y) = self.start;
+        (0..=n).map(move |t| (x + t * sx, y + t * sy))
+    }
+
+    pub fn contains(&self, p: Point) -> bool {
+        let ((sx, sy), n) = self.step();
+        let (dx, dy) = (p.0 - self.start.0, p.1 - self.start.1);
+        if dx * sy != dy * sx {
+            return false;
+        }
+        let t = if sx != 0 { dx / sx } else { dy / sy };
+        (0..=n).contains(&t) && (self.start.0 + t * sx, self.start.1 + t * sy) == p
+    }
+
+    /// The lattice point where two segments that aren't parallel cross, if they do so on one
+    pub fn crossing(&self, other: &Line) -> Option<Point> {
+        let ((dx, dy), k) = self.step();
+        let ((ex, ey), m) = other.step();
+        let det = dx as i128 * ey as i128 - dy as i128 * ex as i128;
+        if det == 0 {
+            return None;
+        }
+        let (wx, wy) = ((other.start.0 - self.start.0) as i128, (other.start.1 - self.start.1) as i128);
+        let t = wx * ey as i128 - wy * ex as i128;
+        let s = wx * dy as i128 - wy * dx as i128;
+        if t % det != 0 || s % det != 0 {
+            return None;
+        }
+        let (t, s) = ((t / det) as i64, (s / det) as i64);
+        if !(0..=k).contains(&t) || !(0..=m).contains(&s) {
+            return None;
+        }
+        Some((self.start.0 + t * dx, self.start.1 + t * dy))
+    }
+
+    fn carrier(&self) -> Carrier {
+        let ((sx, sy), _) = self.step();
+        let dir = if sx < 0 || (sx == 0 && sy < 0) { (-sx, -sy) } else { (sx, sy) };
+        (dir, offset(dir, self.start))
+    }
+
+    /// Where the segment starts and ends along its carrier, in steps
+    fn span(&self) -> (i64, i64) {
+        let (dir, _) = self.carrier();
+        let (a, b) = (position(dir, self.start), position(dir, self.end));
+        (a.min(b), a.max(b))
     }
 }
 
-fn parse_input(input: String) -> Vec<Line> {
-    let r = Regex::new(r"(\d+),(\d+) -> (\d+),(\d+)").unwrap();
-    let mut lines: Vec<Line> = Vec::new();
-    for line in input.lines() {
-        let caps = r.captures(line).unwrap();
-        lines.push(Line {
-            start: (caps.get(1).unwrap().as_str().parse::<i32>().unwrap(),
-                    caps.get(2).unwrap().as_str().parse::<i32>().unwrap()),
-            end: (caps.get(3).unwrap().as_str().parse::<i32>().unwrap(),
-                  caps.get(4).unwrap().as_str().parse::<i32>().unwrap()),
-        });
-    };
-    lines
+fn offset(dir: Point, p: Point) -> i64 {
+    dir.1 * p.0 - dir.0 * p.1
 }
 
+/// How many steps along `dir` the point is; neighbouring lattice points on a carrier differ by exactly 1
+fn position(dir: Point, p: Point) -> i64 {
+    (p.0 * dir.0 + p.1 * dir.1).div_euclid(dir.0 * dir.0 + dir.1 * dir.1)
+}
 
-pub fn part1(input: String) {
-    let lines = parse_input(input);
+/// The stretches covered by at least two of the spans, sorted and not overlapping
+fn covered_twice(spans: &[(i64, i64)]) -> Vec<(i64, i64)> {
+    let mut events = spans.iter().flat_map(|&(a, b)| [(a, 1), (b + 1, -1)]).collect::<Vec<_>>();
+    events.sort();
+    let mut ranges = vec![];
+    let mut depth = 0;
+    let mut start = 0;
+    for (pos, change) in events {
+        let before = depth;
+        depth += change;
+        if before < 2 && depth >= 2 {
+            start = pos;
+        } else if before >= 2 && depth < 2 {
+            ranges.push((start, pos - 1));
+        }
+    }
+    ranges
+}
 
-    let mut vents: HashMap<Point, i32> = HashMap::new();
+/// Count the lattice points covered by at least two of the segments, without visiting every point.
+///
+/// Collinear segments are grouped by the line they lie on and their overlaps are measured as intervals.
+/// Any other pair can only share a single point, so pairs are found by sweeping across x and only testing
+/// segments whose extents overlap. Memory is proportional to the number of segments and crossings, not to
+/// the area they cover.
+pub fn count_overlaps(lines: &[Line]) -> usize {
+    let mut carriers: HashMap<Carrier, Vec<(i64, i64)>> = HashMap::new();
+    for line in lines {
+        carriers.entry(line.carrier()).or_default().push(line.span());
+    }
+    let mut shared: HashMap<Carrier, Vec<(i64, i64)>> = HashMap::new();
+    let mut total = 0;
+    for (carrier, spans) in carriers {
+        let ranges = covered_twice(&spans);
+        total += ranges.iter().map(|(a, b)| (b - a + 1) as usize).sum::<usize>();
+        if !ranges.is_empty() {
+            shared.insert(carrier, ranges);
+        }
+    }
 
-    for line in &lines {
-        if line.is_diagonal() { continue; }
-        for point in line.points() {
-            *vents.entry(point).or_insert(0) += 1;
+    let mut sorted = lines.iter().collect::<Vec<_>>();
+    sorted.sort_by_key(|line| line.start.0.min(line.end.0));
+    let mut active: Vec<&Line> = vec![];
+    let mut crossings = HashSet::new();
+    for line in sorted {
+        let x_min = line.start.0.min(line.end.0);
+        let (y_min, y_max) = (line.start.1.min(line.end.1), line.start.1.max(line.end.1));
+        active.retain(|other| other.start.0.max(other.end.0) >= x_min);
+        for other in &active {
+            if other.start.1.max(other.end.1) < y_min || other.start.1.min(other.end.1) > y_max {
+                continue;
+            }
+            if let Some(p) = line.crossing(other) {
+                crossings.insert(p);
+            }
         }
+        active.push(line);
     }
 
-    let mut total = 0;
-    for (_, n) in vents {
-        if n > 1 {
-            total += 1
+    // A crossing may also sit on stretches that were already counted, possibly on more than one carrier,
+    // and it should be counted exactly once
+    let directions = shared.keys().map(|&(dir, _)| dir).collect::<HashSet<_>>();
+    let times_counted = |p: Point| {
+        directions.iter()
+            .filter(|&&dir| {
+                shared.get(&(dir, offset(dir, p))).is_some_and(|ranges| {
+                    let pos = position(dir, p);
+                    let i = ranges.partition_point(|&(a, _)| a <= pos);
+                    i > 0 && ranges[i - 1].1 >= pos
+                })
+            })
+            .count() as i64
+    };
+    let correction: i64 = crossings.into_iter().map(|p| 1 - times_counted(p)).sum();
+    (total as i64 + correction) as usize
+}
+
+/// How many vents cover each point, for looking at the field rather than just counting it.
+///
+/// Unlike `count_overlaps`, this holds every covered point in memory.
+pub struct Heatmap {
+    counts: HashMap<Point, usize>,
+}
+
+impl Heatmap {
+    pub fn new(lines: &[Line]) -> Heatmap {
+        let mut counts = HashMap::new();
+        for line in lines {
+            trace!(line = %line, "Adding vent");
+            for point in line.points() {
+                *counts.entry(point).or_insert(0) += 1;
+            }
         }
+        Heatmap { counts }
+    }
+
+    pub fn count_at(&self, p: Point) -> usize {
+        self.counts.get(&p).copied().unwrap_or(0)
     }
-    println!("{}", total);
+
+    /// Every point covered by more than `threshold` vents, row by row
+    pub fn above(&self, threshold: usize) -> Vec<Point> {
+        let mut points = self.counts.iter()
+            .filter(|&(_, &n)| n > threshold)
+            .map(|(&p, _)| p)
+            .collect::<Vec<_>>();
+        points.sort_by_key(|&(x, y)| (y, x));
+        points
+    }
+
+    /// The top left and bottom right corners of the covered area
+    fn bounds(&self) -> Option<(Point, Point)> {
+        let xs = self.counts.keys().map(|p| p.0);
+        let ys = self.counts.keys().map(|p| p.1);
+        Some(((xs.clone().min()?, ys.clone().min()?), (xs.max()?, ys.max()?)))
+    }
+
+    /// Render as an 8-bit greyscale PNG, one pixel per point, brighter where more vents overlap
+    pub fn write_png(&self, out: impl Write) -> Result<(), png::EncodingError> {
+        let ((x0, y0), (x1, y1)) = self.bounds().unwrap_or(((0, 0), (0, 0)));
+        let (width, height) = ((x1 - x0 + 1) as u32, (y1 - y0 + 1) as u32);
+        let max = self.counts.values().copied().max().unwrap_or(1);
+        let pixels = (y0..=y1)
+            .flat_map(|y| (x0..=x1).map(move |x| (x, y)))
+            .map(|p| (self.count_at(p) * 255 / max) as u8)
+            .collect::<Vec<_>>();
+
+        let mut encoder = png::Encoder::new(out, width, height);
+        encoder.set_color(png::ColorType::Grayscale);
+        encoder.set_depth(png::BitDepth::Eight);
+        encoder.write_header()?.write_image_data(&pixels)
+    }
+}
+
+/// The covered area as text: `.` where there are no vents, otherwise how many there are, with `#` for 10 or more
+impl fmt::Display for Heatmap {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let Some(((x0, y0), (x1, y1))) = self.bounds() else { return Ok(()) };
+        let rows = (y0..=y1)
+            .map(|y| {
+                (x0..=x1)
+                    .map(|x| match self.count_at((x, y)) {
+                        0 => '.',
+                        n @ 1..=9 => char::from_digit(n as u32, 10).unwrap(),
+                        _ => '#',
+                    })
+                    .collect::<String>()
+            })
+            .collect::<Vec<_>>();
+        write!(f, "{}", rows.join("\n"))
+    }
+}
+
+/// The heatmap for part 1, which leaves diagonal vents out, or part 2, which includes them
+pub fn heatmap(input: &mut dyn BufRead, diagonals: bool) -> Heatmap {
+    let lines = parse_input(input).filter(|line| diagonals || !line.is_diagonal()).collect::<Vec<_>>();
+    Heatmap::new(&lines)
+}
+
+fn parse_input(input: &mut dyn BufRead) -> impl Iterator<Item = Line> + '_ {
+    let r = Regex::new(r"(\d+),(\d+) -> (\d+),(\d+)").unwrap();
+    input.lines().map(move |line| {
+        let line = line.unwrap();
+        let caps = r.captures(&line).unwrap();
+        Line {
+            start: (caps.get(1).unwrap().as_str().parse::<i64>().unwrap(),
+                    caps.get(2).unwrap().as_str().parse::<i64>().unwrap()),
+            end: (caps.get(3).unwrap().as_str().parse::<i64>().unwrap(),
+                  caps.get(4).unwrap().as_str().parse::<i64>().unwrap()),
+        }
+    })
 }
 
-pub fn part2(input: String) {
-    let lines = parse_input(input);
-    let mut vents: HashMap<Point, i32> = HashMap::new();
 
-    for line in &lines {
-        for point in line.points() {
-            *vents.entry(point).or_insert(0) += 1;
+pub fn part1(input: &mut dyn BufRead) -> String {
+    let lines = parse_input(input).filter(|line| !line.is_diagonal()).collect::<Vec<_>>();
+    count_overlaps(&lines).to_string()
+}
+
+pub fn part2(input: &mut dyn BufRead) -> String {
+    let lines = parse_input(input).collect::<Vec<_>>();
+    count_overlaps(&lines).to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gen::Rng;
+
+    fn brute_force(lines: &[Line]) -> usize {
+        let mut vents: HashMap<Point, i32> = HashMap::new();
+        for line in lines {
+            for point in line.points() {
+                *vents.entry(point).or_insert(0) += 1;
+            }
         }
+        vents.values().filter(|&&n| n > 1).count()
     }
 
-    let mut total = 0;
-    for (_, n) in vents {
-        if n > 1 {
-            total += 1
+    fn random_lines(rng: &mut Rng, n: usize, any_slope: bool) -> Vec<Line> {
+        (0..n)
+            .map(|_| {
+                let start = (rng.range(0, 20), rng.range(0, 20));
+                let end = if any_slope {
+                    (rng.range(0, 20), rng.range(0, 20))
+                } else {
+                    let len = rng.range(0, 10);
+                    let (dx, dy) = *rng.choose(&[(1, 0), (0, 1), (1, 1), (1, -1), (-1, 0), (0, -1), (-1, -1), (-1, 1)]);
+                    (start.0 + dx * len, start.1 + dy * len)
+                };
+                Line { start, end }
+            })
+            .collect()
+    }
+
+    #[test]
+    fn matches_brute_force() {
+        let mut rng = Rng::new(5);
+        for round in 0..200 {
+            let lines = random_lines(&mut rng, 1 + round % 40, round % 2 == 1);
+            assert_eq!(count_overlaps(&lines), brute_force(&lines), "{}", lines.iter().map(Line::to_string).collect::<Vec<_>>().join("\n"));
         }
     }
-    println!("{}", total);
-}
\ No newline at end of file
+
+    #[test]
+    fn render() {
+        let input = include_str!("../inputs/examples/day05_sample.txt");
+        assert_eq!(heatmap(&mut input.as_bytes(), false).to_string(), "\
+.......1..
+..1....1..
+..1....1..
+.......1..
+.112111211
+..........
+..........
+..........
+..........
+222111....");
+        assert_eq!(heatmap(&mut input.as_bytes(), true).to_string(), "\
+1.1....11.
+.111...2..
+..2.1.111.
+...1.2.2..
+.112313211
+...1.2....
+..1...1...
+.1.....1..
+1.......1.
+222111....");
+    }
+
+    #[test]
+    fn queries() {
+        let map = heatmap(&mut include_str!("../inputs/examples/day05_sample.txt").as_bytes(), true);
+        assert_eq!(map.count_at((4, 4)), 3);
+        assert_eq!(map.count_at((100, 100)), 0);
+        assert_eq!(map.above(2), vec![(4, 4), (6, 4)]);
+        assert_eq!(map.above(1).len(), 12);
+    }
+
+    #[test]
+    fn png() {
+        let map = heatmap(&mut include_str!("../inputs/examples/day05_sample.txt").as_bytes(), true);
+        let mut out = vec![];
+        map.write_png(&mut out).unwrap();
+        let mut reader = png::Decoder::new(out.as_slice()).read_info().unwrap();
+        let mut pixels = vec![0; reader.output_buffer_size()];
+        let info = reader.next_frame(&mut pixels).unwrap();
+        assert_eq!((info.width, info.height), (10, 10));
+        assert_eq!(pixels[4 * 10 + 4], 255);
+        assert_eq!(pixels[1], 0);
+    }
+
+    #[test]
+    fn crossing() {
+        let a = Line { start: (0, 0), end: (4, 2) };
+        assert_eq!(a.crossing(&Line { start: (2, 0), end: (2, 5) }), Some((2, 1)));
+        // Crosses between lattice points
+        assert_eq!(a.crossing(&Line { start: (1, 0), end: (1, 5) }), None);
+        assert_eq!(a.crossing(&Line { start: (6, 3), end: (8, 4) }), None);
+        assert!(a.contains((4, 2)) && !a.contains((1, 0)) && !a.contains((6, 3)));
+    }
+}
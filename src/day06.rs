@@ -1,61 +1,108 @@
-pub fn part1_naive(input: String) {
-    // First, naive, approach. Doesn't scale well at all.
-    let mut state = input.split(",").map(|x| x.trim().parse::<i32>().unwrap()).collect();
-    fn cycle(state: Vec<i32>) -> Vec<i32> {
-        let mut new_state: Vec<i32> = Vec::new();
-        let mut num_new_fish = 0;
-        for fish in state.clone() {
-            if fish == 0 {
-                num_new_fish += 1;
-                new_state.push(6);
-            } else {
-                new_state.push(fish - 1);
-            }
-        }
-        for _ in 0..num_new_fish {
-            new_state.push(8);
+use num_traits::{CheckedAdd, CheckedMul};
+
+use crate::matrix::{mat_pow, Matrix};
+
+/// How a school of lanternfish grows.
+///
+/// A fish whose timer runs out spawns a newborn with a timer of `delay` and restarts its own at `cycle`.
+/// A day is a linear map on the count of fish at each timer value, which `mat_pow` raises to any number of days.
+pub struct Lanternfish {
+    pub cycle: usize,
+    pub delay: usize,
+}
+
+impl Default for Lanternfish {
+    fn default() -> Lanternfish {
+        Lanternfish { cycle: 6, delay: 8 }
+    }
+}
+
+impl Lanternfish {
+    fn slots(&self) -> usize {
+        self.cycle.max(self.delay) + 1
+    }
+
+    /// `m[to][from]`: how many fish with timer `to` one fish with timer `from` becomes after a day
+    fn transition(&self) -> Matrix<u64> {
+        let n = self.slots();
+        let mut m = vec![vec![0; n]; n];
+        for timer in 1..n {
+            m[timer - 1][timer] = 1;
         }
-        new_state
+        m[self.cycle][0] += 1;
+        m[self.delay][0] += 1;
+        m
     }
 
-    for _ in 0..80 {
-        state = cycle(state);
+    /// How many fish there are after `days`, starting from fish with the given timers, or `None` if the count
+    /// doesn't fit in `T`
+    pub fn population<T>(&self, timers: &[usize], days: u64) -> Option<T>
+    where
+        T: Clone + From<u64> + CheckedAdd + CheckedMul,
+    {
+        let mut counts = vec![0u64; self.slots()];
+        for &timer in timers {
+            assert!(timer < counts.len(), "No fish can have a timer of {}", timer);
+            counts[timer] += 1;
+        }
+        let transition = self.transition().into_iter()
+            .map(|row| row.into_iter().map(T::from).collect())
+            .collect();
+        let m = mat_pow(transition, days)?;
+        m.iter()
+            .flat_map(|row| row.iter().zip(&counts))
+            .try_fold(T::from(0), |total, (n, &count)| total.checked_add(&n.checked_mul(&T::from(count))?))
     }
-    println!("{}", state.len())
 }
 
-fn solve(init_state: [i64; 9], iters: usize) -> i64 {
-    let mut counts = init_state.clone();
-    for _ in 0..iters {
-        let new_fish = counts[0];
-        counts[0] = counts[1];
-        counts[1] = counts[2];
-        counts[2] = counts[3];
-        counts[3] = counts[4];
-        counts[4] = counts[5];
-        counts[5] = counts[6];
-        counts[6] = new_fish + counts[7];
-        counts[7] = counts[8];
-        counts[8] = new_fish;
-    }
-    let result: i64 = counts.iter().sum();
-    result
+fn parse_input(input: &str) -> Vec<usize> {
+    input.split(",").map(|x| x.trim().parse::<usize>().unwrap()).collect()
 }
 
-pub fn part1(input: String) {
-    let mut counts = [0i64; 9];
-    for n in input.split(",").map(|x| x.trim().parse::<usize>().unwrap()) {
-        counts[n] += 1;
-    }
+pub fn part1(input: &str) -> String {
+    Lanternfish::default().population::<u128>(&parse_input(input), 80).unwrap().to_string()
+}
 
-    println!("{}", solve(counts, 80));
+pub fn part2(input: &str) -> String {
+    Lanternfish::default().population::<u128>(&parse_input(input), 256).unwrap().to_string()
 }
 
-pub fn part2(input: String) {
-    let mut counts = [0i64; 9];
-    for n in input.split(",").map(|x| x.trim().parse::<usize>().unwrap()) {
-        counts[n] += 1;
+#[cfg(test)]
+mod tests {
+    use num_bigint::BigUint;
+
+    use super::*;
+
+    /// Step the school one day at a time
+    fn simulate(fish: &Lanternfish, timers: &[usize], days: u64) -> BigUint {
+        let mut counts = vec![BigUint::from(0u64); fish.slots()];
+        for &timer in timers {
+            counts[timer] += 1u64;
+        }
+        for _ in 0..days {
+            let spawning = counts.remove(0);
+            counts.push(BigUint::from(0u64));
+            counts[fish.cycle] += &spawning;
+            counts[fish.delay] += &spawning;
+        }
+        counts.iter().sum()
+    }
+
+    #[test]
+    fn matches_simulation() {
+        let timers = [3, 2, 3, 1, 0];
+        for fish in [Lanternfish::default(), Lanternfish { cycle: 2, delay: 4 }, Lanternfish { cycle: 3, delay: 3 }] {
+            for days in [0, 1, 18, 80, 256, 1000] {
+                assert_eq!(fish.population::<BigUint>(&timers, days), Some(simulate(&fish, &timers, days)));
+            }
+        }
     }
 
-    println!("{}", solve(counts, 256));
-}
\ No newline at end of file
+    #[test]
+    fn overflow() {
+        let fish = Lanternfish::default();
+        assert_eq!(fish.population::<u128>(&[3, 4, 3, 1, 2], 256), Some(26984457539));
+        assert_eq!(fish.population::<u128>(&[3, 4, 3, 1, 2], 2000), None);
+        assert!(fish.population::<BigUint>(&[3, 4, 3, 1, 2], 2000).is_some());
+    }
+}
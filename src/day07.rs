@@ -1,72 +1,118 @@
-use std::collections::HashMap;
+use std::ops::RangeInclusive;
 
-type SumFunction = fn(&Vec<i32>, i32) -> i32;
+/// Fuel to move `dist` steps at one unit per step
+pub fn linear(dist: u64) -> u64 {
+    dist
+}
+
+/// Fuel to move `dist` steps when each step costs one more than the last
+pub fn triangular(dist: u64) -> u64 {
+    dist * (dist + 1) / 2
+}
 
-fn lin_cost(crabs: &Vec<i32>, pos: i32) -> i32 {
-    crabs.iter().map(|crab| (crab - pos).abs()).sum()
+/// Fuel for every crab to move to `pos`
+pub fn total_cost(crabs: &[i64], pos: i64, cost: impl Fn(u64) -> u64) -> u64 {
+    crabs.iter().map(|crab| cost(crab.abs_diff(pos))).sum()
 }
 
-fn cum_cost(crabs: &Vec<i32>, pos: i32) -> i32 {
-    crabs.iter().map(|crab| {
-        let dist = (crab - pos).abs();
-        (1..dist + 1).sum::<i32>()
-    }).sum()
+/// The cheapest way to line the crabs up
+#[derive(Debug, PartialEq)]
+pub struct Alignment {
+    pub cost: u64,
+    /// Every position that costs `cost`; more than one when the optimum is a plateau
+    pub positions: RangeInclusive<i64>,
 }
 
-#[allow(dead_code)]
-fn linear_solve(input: String, sum_fn: SumFunction) {
-    // First attempt; runs part 2 in 7s
-    let crabs: Vec<i32> = input.trim().split(",").map(|crab| crab.parse::<i32>().unwrap()).collect();
+/// Align under any convex cost function, by binary searching for where the total stops going down and
+/// then for where it starts going up again.
+pub fn align(crabs: &[i64], cost: impl Fn(u64) -> u64) -> Alignment {
+    let total = |pos| total_cost(crabs, pos, &cost);
+    let lo = *crabs.iter().min().expect("No crabs to align");
+    let hi = *crabs.iter().max().unwrap();
+    // First position where moving right doesn't help, then first where it hurts
+    let first = partition_point(lo, hi, |pos| total(pos + 1) < total(pos));
+    let last = partition_point(first, hi, |pos| total(pos + 1) <= total(pos));
+    Alignment { cost: total(first), positions: first..=last }
+}
 
-    let min = *crabs.iter().min().unwrap();
-    let max = *crabs.iter().max().unwrap() + 1;
-    let mut best = i32::MAX;
-    for i in min..max {
-        let move_cost = sum_fn(&crabs, i);
-        if move_cost < best {
-            best = move_cost;
-        } else { break; }
+/// The first position in `lo..=hi` where `pred` is false, assuming it is true for a prefix of the range
+fn partition_point(mut lo: i64, mut hi: i64, pred: impl Fn(i64) -> bool) -> i64 {
+    while lo < hi {
+        let mid = lo + (hi - lo) / 2;
+        if pred(mid) { lo = mid + 1 } else { hi = mid }
     }
+    lo
+}
+
+/// Align under linear cost: any median is optimal, so with an even number of crabs every position between
+/// the middle two is.
+pub fn align_linear(crabs: &[i64]) -> Alignment {
+    let mut sorted = crabs.to_vec();
+    sorted.sort_unstable();
+    assert!(!sorted.is_empty(), "No crabs to align");
+    let n = sorted.len();
+    let positions = if n % 2 == 1 { sorted[n / 2]..=sorted[n / 2] } else { sorted[n / 2 - 1]..=sorted[n / 2] };
+    Alignment { cost: total_cost(crabs, *positions.start(), linear), positions }
+}
+
+/// Align under triangular cost: the optimum is always within half a step of the mean, and the total is
+/// strictly convex, so only the integers either side of the mean need checking.
+pub fn align_triangular(crabs: &[i64]) -> Alignment {
+    assert!(!crabs.is_empty(), "No crabs to align");
+    let mean = crabs.iter().map(|&c| c as i128).sum::<i128>().div_euclid(crabs.len() as i128) as i64;
+    let costs = (mean - 1..=mean + 2)
+        .map(|pos| (pos, total_cost(crabs, pos, triangular)))
+        .collect::<Vec<_>>();
+    let cost = costs.iter().map(|&(_, c)| c).min().unwrap();
+    let best = costs.iter().filter(|&&(_, c)| c == cost).map(|&(pos, _)| pos).collect::<Vec<_>>();
+    Alignment { cost, positions: best[0]..=*best.last().unwrap() }
+}
 
-    println!("{}", best);
+fn parse_input(input: &str) -> Vec<i64> {
+    input.trim().split(",").map(|crab| crab.parse::<i64>().unwrap()).collect()
 }
 
-fn solve(input: String, sum_fn: SumFunction) {
-    // Second attempt with binary search;
-    let crabs: Vec<i32> = input.trim().split(",").map(|crab| crab.parse::<i32>().unwrap()).collect();
+pub fn part1(input: &str) -> String {
+    align_linear(&parse_input(input)).cost.to_string()
+}
 
-    let mut slants: HashMap<i32, i32> = HashMap::new();
+pub fn part2(input: &str) -> String {
+    align_triangular(&parse_input(input)).cost.to_string()
+}
 
-    let mut slant = |pos: i32| {
-        *slants.entry(pos).or_insert(
-            {
-                let cost = sum_fn(&crabs, pos);
-                let left_cost = sum_fn(&crabs, pos - 1);
-                let right_cost = sum_fn(&crabs, pos + 1);
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gen::Rng;
 
-                if cost < left_cost && cost < right_cost { 0 } else if left_cost < cost { -1 } else if right_cost < cost { 1 } else { panic!("How did we get here? {} {} {}", left_cost, cost, right_cost) }
-            })
-    };
+    fn brute_force(crabs: &[i64], cost: impl Fn(u64) -> u64) -> Alignment {
+        let lo = *crabs.iter().min().unwrap();
+        let hi = *crabs.iter().max().unwrap();
+        let costs = (lo..=hi).map(|pos| (pos, total_cost(crabs, pos, &cost))).collect::<Vec<_>>();
+        let cost = costs.iter().map(|&(_, c)| c).min().unwrap();
+        let best = costs.iter().filter(|&&(_, c)| c == cost).map(|&(pos, _)| pos).collect::<Vec<_>>();
+        Alignment { cost, positions: best[0]..=*best.last().unwrap() }
+    }
 
-    let mut left = *crabs.iter().min().unwrap();
-    let mut right = *crabs.iter().max().unwrap();
-    let mut pos = (left + right) / 2;
+    #[test]
+    fn plateaus() {
+        assert_eq!(align_linear(&[1, 3]), Alignment { cost: 2, positions: 1..=3 });
+        assert_eq!(align(&[1, 3], linear), Alignment { cost: 2, positions: 1..=3 });
+        assert_eq!(align_triangular(&[0, 1]), Alignment { cost: 1, positions: 0..=1 });
+        assert_eq!(align(&[0, 1], triangular), Alignment { cost: 1, positions: 0..=1 });
+    }
 
-    while slant(pos) != 0 {
-        if slant(pos) == -1 {
-            right = pos;
-        } else {
-            left = pos;
+    #[test]
+    fn matches_brute_force() {
+        let mut rng = Rng::new(7);
+        for round in 0..300 {
+            let crabs = (0..1 + round % 20).map(|_| rng.range(-30, 30)).collect::<Vec<_>>();
+            assert_eq!(align_linear(&crabs), brute_force(&crabs, linear), "{:?}", crabs);
+            assert_eq!(align(&crabs, linear), brute_force(&crabs, linear), "{:?}", crabs);
+            assert_eq!(align_triangular(&crabs), brute_force(&crabs, triangular), "{:?}", crabs);
+            assert_eq!(align(&crabs, triangular), brute_force(&crabs, triangular), "{:?}", crabs);
+            let cubic = |d: u64| d * d * d;
+            assert_eq!(align(&crabs, cubic), brute_force(&crabs, cubic), "{:?}", crabs);
         }
-        pos = (left + right) / 2;
     }
-    println!("{}", sum_fn(&crabs, pos));
-}
-
-pub fn part1(input: String) {
-    solve(input, lin_cost)
 }
-
-pub fn part2(input: String) {
-    solve(input, cum_cost)
-}
\ No newline at end of file
@@ -1,12 +1,260 @@
 use std::collections::{HashMap, HashSet};
+use std::fmt;
 
-fn set(s: &String) -> HashSet<char> {
-    s.chars().collect::<HashSet<char>>()
+/// Why a display couldn't be decoded
+#[derive(Debug, PartialEq)]
+pub enum DecodeError {
+    /// A pattern uses a wire the font doesn't have
+    UnknownWire(char),
+    /// No wiring turns every pattern into a glyph
+    Contradiction,
+    /// More than one wiring fits, and they read the output differently
+    Ambiguous,
 }
 
-pub fn part1(input: String) {
+impl fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DecodeError::UnknownWire(wire) => write!(f, "Unknown wire {}", wire),
+            DecodeError::Contradiction => write!(f, "No wiring fits the patterns"),
+            DecodeError::Ambiguous => write!(f, "More than one wiring fits the patterns"),
+        }
+    }
+}
+
+/// The glyphs a display can show, each as the set of segments it lights.
+///
+/// Segments and wires are both named by letters starting from `a`, with a bit per letter.
+pub struct Font {
+    segments: usize,
+    glyphs: Vec<(char, u32)>,
+}
+
+impl Font {
+    pub fn new(segments: usize, glyphs: &[(char, &str)]) -> Font {
+        assert!(segments <= 32, "Fonts can have at most 32 segments");
+        let glyphs = glyphs.iter()
+            .map(|&(symbol, lit)| (symbol, mask(lit, segments).expect("Glyph uses a segment outside the font")))
+            .collect();
+        Font { segments, glyphs }
+    }
+
+    pub fn seven_segment() -> Font {
+        Font::new(7, &[
+            ('0', "abcefg"),
+            ('1', "cf"),
+            ('2', "acdeg"),
+            ('3', "acdfg"),
+            ('4', "bcdf"),
+            ('5', "abdfg"),
+            ('6', "abdefg"),
+            ('7', "acf"),
+            ('8', "abcdefg"),
+            ('9', "abcdfg"),
+        ])
+    }
+
+    /// How many segments each glyph lights, for the sizes no other glyph shares
+    pub fn unique_sizes(&self) -> Vec<usize> {
+        let sizes = self.glyphs.iter().map(|&(_, g)| g.count_ones() as usize).collect::<Vec<_>>();
+        sizes.iter().copied().filter(|&n| sizes.iter().filter(|&&m| m == n).count() == 1).collect()
+    }
+
+    /// Work out which wire drives which segment from the scrambled patterns and read the output.
+    ///
+    /// Each distinct pattern is matched with a glyph of the same size, which limits the segments each of its
+    /// wires (and every other wire) can drive. Matches are searched most-constrained first, and a branch is
+    /// dropped as soon as the wires can no longer be given distinct segments.
+    pub fn decode(&self, patterns: &[&str], output: &[&str]) -> Result<Decoded, DecodeError> {
+        let to_mask = |p: &&str| mask(p, self.segments);
+        let output_masks = output.iter().map(to_mask).collect::<Result<Vec<_>, _>>()?;
+        let mut distinct = patterns.iter().map(to_mask).collect::<Result<Vec<_>, _>>()?;
+        distinct.extend(&output_masks);
+        let distinct = distinct.into_iter().collect::<HashSet<_>>().into_iter().collect::<Vec<_>>();
+
+        let mut search = Search {
+            font: self,
+            patterns: &distinct,
+            output: &output_masks,
+            readings: HashMap::new(),
+        };
+        let all = u32::MAX.checked_shr(32 - self.segments as u32).unwrap_or(0);
+        search.run(&mut vec![None; distinct.len()], &vec![all; self.segments]);
+
+        let mut readings = search.readings.into_iter();
+        let (reading, driven) = match (readings.next(), readings.next()) {
+            (None, _) => return Err(DecodeError::Contradiction),
+            (Some(found), None) => found,
+            (Some(_), Some(_)) => return Err(DecodeError::Ambiguous),
+        };
+        let wiring = driven.iter().map(|&s| letter(s)).collect::<String>();
+        let unscramble = |p: &str| {
+            let mut lit = p.chars().map(|w| wiring.as_bytes()[w as usize - 'a' as usize] as char).collect::<Vec<_>>();
+            lit.sort_unstable();
+            lit.into_iter().collect()
+        };
+        Ok(Decoded {
+            scrambled: output.iter().map(|&p| p.to_string()).collect(),
+            unscrambled: output.iter().map(|p| unscramble(p)).collect(),
+            wiring,
+            reading,
+        })
+    }
+}
+
+fn mask(pattern: &str, segments: usize) -> Result<u32, DecodeError> {
+    pattern.chars().try_fold(0, |mask, wire| {
+        let bit = (wire as u32).wrapping_sub('a' as u32);
+        if (bit as usize) < segments { Ok(mask | 1 << bit) } else { Err(DecodeError::UnknownWire(wire)) }
+    })
+}
+
+struct Search<'a> {
+    font: &'a Font,
+    patterns: &'a [u32],
+    output: &'a [u32],
+    /// Each different way the output has been read so far, with a wiring that reads it that way; two is
+    /// enough to know it's ambiguous
+    readings: HashMap<String, Vec<usize>>,
+}
+
+impl Search<'_> {
+    /// `matched[p]` is the glyph pattern `p` has been matched with, and `domains[w]` the segments wire `w`
+    /// could still drive
+    fn run(&mut self, matched: &mut Vec<Option<usize>>, domains: &[u32]) {
+        if self.readings.len() > 1 {
+            return;
+        }
+        // Pick the unmatched pattern with the fewest glyphs left to try
+        let next = (0..self.patterns.len())
+            .filter(|&p| matched[p].is_none())
+            .map(|p| (p, self.options(self.patterns[p], domains)))
+            .min_by_key(|(_, options)| options.len());
+        let Some((p, options)) = next else {
+            let reading = self.output.iter()
+                .map(|o| self.font.glyphs[matched[self.patterns.iter().position(|p| p == o).unwrap()].unwrap()].0)
+                .collect();
+            let driven = wiring(domains).expect("Every match keeps a wiring possible");
+            self.readings.entry(reading).or_insert(driven);
+            return;
+        };
+        for (glyph, narrowed) in options {
+            matched[p] = Some(glyph);
+            self.run(matched, &narrowed);
+            matched[p] = None;
+        }
+    }
+
+    /// The glyphs `pattern` could be, with the wire domains that would leave
+    fn options(&self, pattern: u32, domains: &[u32]) -> Vec<(usize, Vec<u32>)> {
+        self.font.glyphs.iter()
+            .enumerate()
+            .filter(|(_, &(_, glyph))| glyph.count_ones() == pattern.count_ones())
+            .filter_map(|(g, &(_, glyph))| {
+                let narrowed = domains.iter()
+                    .enumerate()
+                    .map(|(w, &domain)| if pattern & 1 << w != 0 { domain & glyph } else { domain & !glyph })
+                    .collect::<Vec<_>>();
+                wiring(&narrowed).map(|_| (g, narrowed))
+            })
+            .collect()
+    }
+}
+
+/// Give every wire a different segment from its domain, by finding a bipartite matching. Returns the segment
+/// each wire drives.
+fn wiring(domains: &[u32]) -> Option<Vec<usize>> {
+    fn assign(w: usize, domains: &[u32], driver: &mut [Option<usize>], seen: &mut u32) -> bool {
+        for s in 0..domains.len() {
+            if domains[w] & 1 << s == 0 || *seen & 1 << s != 0 {
+                continue;
+            }
+            *seen |= 1 << s;
+            if driver[s].is_none_or(|other| assign(other, domains, driver, seen)) {
+                driver[s] = Some(w);
+                return true;
+            }
+        }
+        false
+    }
+
+    let mut driver = vec![None; domains.len()];
+    if !(0..domains.len()).all(|w| assign(w, domains, &mut driver, &mut 0)) {
+        return None;
+    }
+    let mut driven = vec![0; domains.len()];
+    for (s, w) in driver.into_iter().enumerate() {
+        driven[w.unwrap()] = s;
+    }
+    Some(driven)
+}
+
+fn letter(segment: usize) -> char {
+    (b'a' + segment as u8) as char
+}
+
+/// One display after decoding
+#[derive(Debug, PartialEq)]
+pub struct Decoded {
+    /// The segment each wire drives, in wire order. When the patterns don't pin every wire down this is one
+    /// of the wirings that fit.
+    pub wiring: String,
+    /// The output patterns as they came in
+    pub scrambled: Vec<String>,
+    /// The output patterns with the wiring undone, as sorted segment letters
+    pub unscrambled: Vec<String>,
+    pub reading: String,
+}
+
+/// Draws the scrambled output next to the unscrambled one, as seven-segment digits:
+///
+/// ```text
+/// cfgabde -> 5353
+///      _       _  |  _   _   _   _
+/// |_| |_| |_| |_| | |_   _| |_   _|
+/// | |   | | |   | |  _|  _|  _|  _|
+/// ```
+///
+/// Only meaningful for seven-segment fonts; other segments aren't drawn.
+impl fmt::Display for Decoded {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} -> {}", self.wiring, self.reading)?;
+        let scrambled = seven_segment_art(&self.scrambled);
+        let unscrambled = seven_segment_art(&self.unscrambled);
+        for (left, right) in scrambled.iter().zip(&unscrambled) {
+            write!(f, "\n{} | {}", left, right.trim_end())?;
+        }
+        Ok(())
+    }
+}
+
+/// Three rows of ASCII art with one digit per pattern, reading segments `a` to `g` as top, upper left, upper
+/// right, middle, lower left, lower right and bottom
+fn seven_segment_art(patterns: &[String]) -> [String; 3] {
+    // Where each segment is drawn in a three-by-three cell
+    const CELLS: [(usize, usize, char); 7] =
+        [(0, 1, '_'), (1, 0, '|'), (1, 2, '|'), (1, 1, '_'), (2, 0, '|'), (2, 2, '|'), (2, 1, '_')];
+    let mut rows = [String::new(), String::new(), String::new()];
+    for pattern in patterns {
+        let mut cell = [[' '; 3]; 3];
+        for (segment, &(row, col, ch)) in CELLS.iter().enumerate() {
+            if pattern.contains(letter(segment)) {
+                cell[row][col] = ch;
+            }
+        }
+        for (row, drawn) in rows.iter_mut().zip(cell) {
+            if !row.is_empty() {
+                row.push(' ');
+            }
+            row.extend(drawn);
+        }
+    }
+    rows
+}
+
+pub fn part1(input: &str) -> String {
     let lines: Vec<&str> = input.trim().split("\n").collect();
-    let lengths = vec![2usize, 4, 3, 7];
+    let lengths = Font::seven_segment().unique_sizes();
     let mut total = 0usize;
     for line in lines {
         let (_, outputs) = line.split_once(" | ").unwrap();
@@ -14,89 +262,125 @@ pub fn part1(input: String) {
         outputs.retain(|x| lengths.contains(x));
         total += outputs.len();
     }
-    println!("{}", total);
+    total.to_string()
 }
 
-pub fn part2(input: String) {
-    let lines: Vec<&str> = input.trim().split("\n").collect();
-    let nums: Vec<usize> = lines.iter().map(|line| {
-        let mut mapping: HashMap<String, usize> = HashMap::new();
-        let (signals, output) = line.split_once(" | ").unwrap();
-        let mut signals: Vec<String> = signals.split(" ")
-            .map(|s| {
-                let mut c: Vec<char> = s.chars().collect();
-                c.sort();
-                c.iter().collect()
-            }).collect();
-        signals.sort_by_key(|x| x.len());
-
-        mapping.insert(signals[0].clone(), 1);
-        mapping.insert(signals[1].clone(), 7);
-        mapping.insert(signals[2].clone(), 4);
-        mapping.insert(signals[9].clone(), 8);
-
-        let mut unknown_235 = vec![&signals[3], &signals[4], &signals[5]];
-        let mut unknown_069 = vec![&signals[6], &signals[7], &signals[8]];
-
-        // 0, 6, and 9 all have 6 segments. 6 is the only one that doesn't have both of 1's segments
-        let one_segments = set(&signals[0]);
-        let mut six_chars: HashSet<char> = HashSet::new();
-        for signal in unknown_069.clone() {
-            let segments = set(signal);
-            if segments.intersection(&one_segments).count() < one_segments.len() {
-                mapping.insert(signal.clone(), 6);
-                six_chars = set(signal);
-                unknown_069.retain(|&x| x != signal);
-                break;
-            }
-        }
+/// Decode every entry of the puzzle input with the standard seven-segment font
+pub fn decode_entries(input: &str) -> Vec<Result<Decoded, DecodeError>> {
+    let font = Font::seven_segment();
+    input.lines()
+        .map(|line| {
+            let (signals, output) = line.split_once(" | ").unwrap();
+            let signals = signals.split_whitespace().collect::<Vec<_>>();
+            let output = output.split_whitespace().collect::<Vec<_>>();
+            font.decode(&signals, &output)
+        })
+        .collect()
+}
 
-        // Solve 2, 3, and 5
-        // 3 is the only one that has both of 1's digits
-        for signal in unknown_235.clone() {
-            let segments = set(signal);
-            if segments.intersection(&one_segments).count() == one_segments.len() {
-                mapping.insert(signal.clone(), 3);
-                unknown_235.retain(|&x| x != signal);
-                break;
-            }
-        }
-        // 5 is the same as 6 with one segment missing
-        for signal in unknown_235.clone() {
-            let segments = set(signal);
-            if segments.intersection(&six_chars).count() == 5 {
-                mapping.insert(signal.clone(), 5);
-                unknown_235.retain(|&x| x != signal);
-                break;
-            }
-        }
-        // 2 is the only other 5-segment digit
-        mapping.insert(unknown_235.pop().unwrap().clone(), 2);
-
-        // Only 9 and 0 remain. 9 is the one that has all the same segments as 4.
-        let four_segments = set(&signals[2]);
-        for signal in unknown_069.clone() {
-            let segments = set(signal);
-            if segments.intersection(&four_segments).count() == four_segments.len() {
-                mapping.insert(signal.clone(), 9);
-                unknown_069.retain(|&x| x != signal);
-                break;
+pub fn part2(input: &str) -> String {
+    decode_entries(input).into_iter()
+        .zip(input.lines())
+        .map(|(decoded, line)| match decoded {
+            Ok(decoded) => decoded.reading.parse::<usize>().unwrap(),
+            Err(e) => panic!("{}: {}", e, line),
+        })
+        .sum::<usize>()
+        .to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gen::Rng;
+
+    #[test]
+    fn errors() {
+        let font = Font::seven_segment();
+        assert_eq!(font.decode(&["abcde"], &["abcde"]).map(|d| d.reading), Err(DecodeError::Ambiguous));
+        // Only 1 lights two segments, so two different two-wire patterns can't both be it
+        assert_eq!(font.decode(&["ab", "cd"], &["ab"]).map(|d| d.reading), Err(DecodeError::Contradiction));
+        assert_eq!(font.decode(&["ab"], &["az"]).map(|d| d.reading), Err(DecodeError::UnknownWire('z')));
+        // Ambiguous wiring is fine as long as the output reads the same either way
+        assert_eq!(font.decode(&["ab"], &["ba"]).map(|d| d.reading), Ok(String::from("1")));
+    }
+
+    #[test]
+    fn alternate_font() {
+        // 6, 7 and 9 drawn with their optional segments
+        let font = Font::new(7, &[
+            ('0', "abcefg"),
+            ('1', "cf"),
+            ('2', "acdeg"),
+            ('3', "acdfg"),
+            ('4', "bcdf"),
+            ('5', "abdfg"),
+            ('6', "abdefg"),
+            ('7', "abcf"),
+            ('8', "abcdefg"),
+            ('9', "abcdfg"),
+        ]);
+        // Wire x drives segment "gfedcba"[x]
+        let scramble = |s: &str| s.chars().map(|c| (b'g' - (c as u8 - b'a')) as char).collect::<String>();
+        let patterns = ["abcefg", "cf", "acdeg", "acdfg", "bcdf", "abdfg", "abdefg", "abcf", "abcdefg", "abcdfg"]
+            .map(scramble);
+        let patterns = patterns.iter().map(String::as_str).collect::<Vec<_>>();
+        assert_eq!(font.decode(&patterns, &[patterns[7], patterns[4], patterns[0]]).map(|d| d.reading), Ok(String::from("740")));
+    }
+
+    #[test]
+    fn sixteen_segments() {
+        let mut rng = Rng::new(16);
+        let segments = "abcdefghijklmnop".chars().collect::<Vec<_>>();
+        // Random distinct glyphs
+        let mut glyphs: Vec<(char, String)> = vec![];
+        while glyphs.len() < 26 {
+            let lit = segments.iter().filter(|_| rng.chance(0.5)).collect::<String>();
+            if !lit.is_empty() && glyphs.iter().all(|(_, g)| *g != lit) {
+                glyphs.push(((b'A' + glyphs.len() as u8) as char, lit));
             }
         }
-        mapping.insert(unknown_069.pop().unwrap().clone(), 0);
-        let output: Vec<String> = output.split(" ")
-            .map(|s| {
-                let mut c: Vec<char> = s.chars().collect();
-                c.sort();
-                c.iter().collect()
-            }).collect();
-
-        let mut result = 0 as usize;
-        for n in output {
-            let value = mapping.get(&*n).unwrap();
-            result = 10 * result + value;
-        }
-        result
-    }).collect();
-    println!("{}", nums.iter().sum::<usize>());
-}
\ No newline at end of file
+        let font = Font::new(16, &glyphs.iter().map(|(c, g)| (*c, g.as_str())).collect::<Vec<_>>());
+
+        let mut wiring = segments.clone();
+        rng.shuffle(&mut wiring);
+        let scrambled = glyphs.iter()
+            .map(|(_, g)| g.chars().map(|c| wiring[c as usize - 'a' as usize]).collect::<String>())
+            .collect::<Vec<_>>();
+        let patterns = scrambled.iter().map(String::as_str).collect::<Vec<_>>();
+        let output = [patterns[7], patterns[4], patterns[11], patterns[11], patterns[14]];
+        assert_eq!(font.decode(&patterns, &output).map(|d| d.reading), Ok(String::from("HELLO")));
+    }
+
+    #[test]
+    fn thirty_two_segments() {
+        // Glyph k lights the first k + 1 segments, so every glyph has its own size
+        let wires = (0..32).map(letter).collect::<Vec<_>>();
+        let lit = (1..=32).map(|n| wires[..n].iter().collect::<String>()).collect::<Vec<_>>();
+        let glyphs = lit.iter().enumerate().map(|(k, g)| ((b'0' + k as u8) as char, g.as_str())).collect::<Vec<_>>();
+        let font = Font::new(32, &glyphs);
+
+        let scrambled = lit.iter()
+            .map(|g| g.chars().map(|c| wires[31 - (c as usize - 'a' as usize)]).collect::<String>())
+            .collect::<Vec<_>>();
+        let patterns = scrambled.iter().map(String::as_str).collect::<Vec<_>>();
+        let output = [patterns[31], patterns[0], patterns[9]];
+        let decoded = font.decode(&patterns, &output).unwrap();
+        assert_eq!(decoded.reading, "O09");
+        assert_eq!(decoded.wiring, wires.iter().rev().collect::<String>());
+    }
+
+    #[test]
+    fn render() {
+        let line = "acedgfb cdfbe gcdfa fbcad dab cefabd cdfgeb eafb cagedb ab | cdfeb fcadb cdfeb cdbaf";
+        let decoded = decode_entries(line).remove(0).unwrap();
+        assert_eq!(decoded.wiring, "cfgabde");
+        assert_eq!(decoded.unscrambled, ["abdfg", "acdfg", "abdfg", "acdfg"]);
+        assert_eq!(decoded.to_string(), [
+            "cfgabde -> 5353",
+            "     _       _  |  _   _   _   _",
+            "|_| |_| |_| |_| | |_   _| |_   _|",
+            "| |   | | |   | |  _|  _|  _|  _|",
+        ].join("\n"));
+    }
+}
@@ -1,81 +1,456 @@
-use std::collections::{HashMap, HashSet, VecDeque};
+use std::collections::{BTreeMap, VecDeque};
+use std::fmt;
 
-type Coord = (i32, i32);
+/// (row, column)
+pub type Coord = (usize, usize);
 
+/// The ridge height in the puzzle: locations this high are in no basin
+pub const RIDGE: u8 = 9;
 
-fn parse_input(input: String) -> HashMap<Coord, i32> {
-    let mut height_map: HashMap<Coord, i32> = HashMap::new();
+#[derive(Debug, PartialEq)]
+pub enum ParseError {
+    InvalidHeight { row: usize, col: usize, found: char },
+    /// A row with a different width from the first one
+    Ragged { row: usize },
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ParseError::InvalidHeight { row, col, found } => {
+                write!(f, "Invalid height {:?} at row {}, column {}", found, row, col)
+            }
+            ParseError::Ragged { row } => write!(f, "Row {} is not the same width as the first row", row),
+        }
+    }
+}
+
+/// Heights in a dense row-major array
+pub struct Heightmap {
+    width: usize,
+    height: usize,
+    heights: Vec<u8>,
+}
+
+impl Heightmap {
+    /// One row of digits per line. Blank lines are ignored, so a trailing newline doesn't add a row.
+    pub fn parse(input: &str) -> Result<Heightmap, ParseError> {
+        let mut heights = vec![];
+        let mut width = None;
+        for (row, line) in input.lines().map(str::trim_end).filter(|line| !line.is_empty()).enumerate() {
+            let start = heights.len();
+            for (col, found) in line.chars().enumerate() {
+                let height = found.to_digit(10).ok_or(ParseError::InvalidHeight { row, col, found })?;
+                heights.push(height as u8);
+            }
+            // Counted in digits rather than bytes, so anything that isn't a digit is reported as that instead
+            let row_width = heights.len() - start;
+            if *width.get_or_insert(row_width) != row_width {
+                return Err(ParseError::Ragged { row });
+            }
+        }
+        let width = width.unwrap_or(0);
+        let height = heights.len().checked_div(width).unwrap_or(0);
+        Ok(Heightmap { width, height, heights })
+    }
+
+    pub fn get(&self, (row, col): Coord) -> Option<u8> {
+        (row < self.height && col < self.width).then(|| self.heights[row * self.width + col])
+    }
+
+    fn coord(&self, i: usize) -> Coord {
+        (i / self.width, i % self.width)
+    }
+
+    /// Indices of the orthogonal neighbours of index `i`
+    fn neighbors(&self, i: usize) -> impl Iterator<Item = usize> {
+        let (row, col) = self.coord(i);
+        let width = self.width;
+        [
+            (row > 0).then(|| i - width),
+            (row + 1 < self.height).then(|| i + width),
+            (col > 0).then(|| i - 1),
+            (col + 1 < width).then(|| i + 1),
+        ].into_iter().flatten()
+    }
+
+    /// Locations lower than every neighbour, in row-major order
+    pub fn low_points(&self) -> Vec<Coord> {
+        (0..self.heights.len())
+            .filter(|&i| self.neighbors(i).all(|n| self.heights[n] > self.heights[i]))
+            .map(|i| self.coord(i))
+            .collect()
+    }
+
+    /// Label every basin: the regions of locations below `ridge` joined orthogonally.
+    ///
+    /// Each location is unioned with the one to its right and the one below it, so one pass over the array
+    /// finds every basin. Basins are numbered in row-major order of their first location.
+    pub fn basins(&self, ridge: u8) -> Basins<'_> {
+        let mut sets = DisjointSets::new(self.heights.len());
+        let open = |i: usize| self.heights[i] < ridge;
+        for i in (0..self.heights.len()).filter(|&i| open(i)) {
+            if i % self.width + 1 < self.width && open(i + 1) {
+                sets.union(i, i + 1);
+            }
+            if i + self.width < self.heights.len() && open(i + self.width) {
+                sets.union(i, i + self.width);
+            }
+        }
+
+        let mut label_of_root = vec![None; self.heights.len()];
+        let mut labels = vec![None; self.heights.len()];
+        let mut sizes = vec![];
+        for i in (0..self.heights.len()).filter(|&i| open(i)) {
+            let root = sets.find(i);
+            let label = *label_of_root[root].get_or_insert_with(|| {
+                sizes.push(0);
+                sizes.len() - 1
+            });
+            labels[i] = Some(label);
+            sizes[label] += 1;
+        }
+        Basins { map: self, labels, sizes }
+    }
+
+    /// Follow steepest descent from every location.
+    ///
+    /// Water moves to the lowest neighbour that is strictly lower, ties going to the first in row-major
+    /// order. On a plateau of equal heights it moves towards the nearest plateau location that can drain
+    /// lower; a plateau with no way down is a sink, drained through its first location.
+    pub fn watershed(&self) -> Watershed<'_> {
+        let n = self.heights.len();
+        let h = &self.heights;
+        let mut downstream = (0..n)
+            .map(|i| self.neighbors(i).filter(|&j| h[j] < h[i]).min_by_key(|&j| (h[j], j)))
+            .collect::<Vec<_>>();
+
+        let mut seen = vec![false; n];
+        let mut reached = vec![false; n];
+        for start in 0..n {
+            if seen[start] || downstream[start].is_some() {
+                continue;
+            }
+            let mut plateau = vec![start];
+            seen[start] = true;
+            let mut k = 0;
+            while k < plateau.len() {
+                let i = plateau[k];
+                for j in self.neighbors(i) {
+                    if !seen[j] && h[j] == h[i] {
+                        seen[j] = true;
+                        plateau.push(j);
+                    }
+                }
+                k += 1;
+            }
+            plateau.sort_unstable();
+
+            let mut queue = plateau.iter().copied().filter(|&i| downstream[i].is_some()).collect::<VecDeque<_>>();
+            if queue.is_empty() {
+                queue.push_back(plateau[0]);
+            }
+            for &i in &queue {
+                reached[i] = true;
+            }
+            while let Some(i) = queue.pop_front() {
+                for j in self.neighbors(i) {
+                    if h[j] == h[i] && !reached[j] {
+                        reached[j] = true;
+                        downstream[j] = Some(i);
+                        queue.push_back(j);
+                    }
+                }
+            }
+        }
 
-    for (r, line) in input.split("\n").enumerate() {
-        for (c, ch) in line.chars().enumerate() {
-            let coord: Coord = (r as i32, c as i32);
-            height_map.insert(coord, ch.to_string().parse::<i32>().unwrap());
+        let sinks = (0..n).filter(|&i| downstream[i].is_none()).collect::<Vec<_>>();
+        let mut labels = vec![None; n];
+        for (label, &sink) in sinks.iter().enumerate() {
+            labels[sink] = Some(label);
+        }
+        for start in 0..n {
+            let mut path = vec![];
+            let mut i = start;
+            while labels[i].is_none() {
+                path.push(i);
+                i = downstream[i].unwrap();
+            }
+            for p in path {
+                labels[p] = labels[i];
+            }
+        }
+
+        // Push counts downstream, starting from the locations nothing drains into
+        let mut inflow = vec![0; n];
+        for &j in downstream.iter().flatten() {
+            inflow[j] += 1;
+        }
+        let mut accumulation = vec![1; n];
+        let mut ready = (0..n).filter(|&i| inflow[i] == 0).collect::<Vec<_>>();
+        while let Some(i) = ready.pop() {
+            if let Some(j) = downstream[i] {
+                accumulation[j] += accumulation[i];
+                inflow[j] -= 1;
+                if inflow[j] == 0 {
+                    ready.push(j);
+                }
+            }
+        }
+
+        Watershed {
+            map: self,
+            labels: labels.into_iter().map(Option::unwrap).collect(),
+            sinks,
+            accumulation,
         }
     }
-    height_map
 }
 
-pub fn part1(input: String) {
-    let height_map = parse_input(input);
-    let mut risk = 0;
-    for ((r, c), value) in height_map.clone() {
-        let &north = height_map.get(&(r - 1, c)).unwrap_or(&i32::MAX);
-        let &south = height_map.get(&(r + 1, c)).unwrap_or(&i32::MAX);
-        let &east = height_map.get(&(r, c + 1)).unwrap_or(&i32::MAX);
-        let &west = height_map.get(&(r, c - 1)).unwrap_or(&i32::MAX);
-        if north > value && south > value && east > value && west > value {
-            risk += value + 1;
+/// Union-find with path halving and union by size
+struct DisjointSets {
+    parent: Vec<usize>,
+    size: Vec<usize>,
+}
+
+impl DisjointSets {
+    fn new(n: usize) -> DisjointSets {
+        DisjointSets { parent: (0..n).collect(), size: vec![1; n] }
+    }
+
+    fn find(&mut self, mut i: usize) -> usize {
+        while self.parent[i] != i {
+            self.parent[i] = self.parent[self.parent[i]];
+            i = self.parent[i];
         }
+        i
+    }
+
+    fn union(&mut self, a: usize, b: usize) {
+        let (mut a, mut b) = (self.find(a), self.find(b));
+        if a == b {
+            return;
+        }
+        if self.size[a] < self.size[b] {
+            std::mem::swap(&mut a, &mut b);
+        }
+        self.parent[b] = a;
+        self.size[a] += self.size[b];
+    }
+}
+
+/// Every basin of a heightmap
+pub struct Basins<'a> {
+    map: &'a Heightmap,
+    /// The basin each location belongs to, if any
+    labels: Vec<Option<usize>>,
+    sizes: Vec<usize>,
+}
+
+impl Basins<'_> {
+    pub fn basin_of(&self, (row, col): Coord) -> Option<usize> {
+        self.map.get((row, col)).and_then(|_| self.labels[row * self.map.width + col])
+    }
+
+    /// How many locations are in each basin, indexed by basin
+    pub fn sizes(&self) -> &[usize] {
+        &self.sizes
+    }
+
+    /// The locations in `basin`, in row-major order
+    pub fn members(&self, basin: usize) -> Vec<Coord> {
+        (0..self.labels.len())
+            .filter(|&i| self.labels[i] == Some(basin))
+            .map(|i| self.map.coord(i))
+            .collect()
+    }
+
+    /// The ridge locations bordering `basin`, in row-major order
+    pub fn rim(&self, basin: usize) -> Vec<Coord> {
+        (0..self.labels.len())
+            .filter(|&i| self.labels[i].is_none())
+            .filter(|&i| self.map.neighbors(i).any(|n| self.labels[n] == Some(basin)))
+            .map(|i| self.map.coord(i))
+            .collect()
     }
-    println!("{}", risk);
 }
 
-type Basin = HashSet<Coord>;
+/// Where water flowing downhill ends up, from `Heightmap::watershed`
+pub struct Watershed<'a> {
+    map: &'a Heightmap,
+    /// The sink each location drains into
+    labels: Vec<usize>,
+    /// The location each sink drains through, in row-major order
+    sinks: Vec<usize>,
+    /// How many locations' water flows through each location, itself included
+    accumulation: Vec<usize>,
+}
 
-fn neighbors(c: &Coord) -> [Coord; 4] {
-    return [
-        (c.0 - 1, c.1),
-        (c.0 + 1, c.1),
-        (c.0, c.1 - 1),
-        (c.0, c.1 + 1),
-    ];
+/// The lowest pass between two neighbouring watersheds
+#[derive(Debug, PartialEq)]
+pub struct Saddle {
+    pub watersheds: (usize, usize),
+    pub at: Coord,
+    pub height: u8,
 }
 
-fn explore_basin(map: &HashMap<Coord, i32>, start: &Coord) -> Basin {
-    let mut basin: Basin = HashSet::new();
-    basin.insert(*start);
-    let mut check: VecDeque<Coord> = VecDeque::new();
-    check.extend(neighbors(start));
-    while !check.is_empty() {
-        let coord = &check.pop_front().unwrap();
-        if basin.contains(coord) { continue; }
-        if !map.contains_key(coord) { continue; }
-        if map.get(coord).unwrap() == RIDGE { continue; }
+impl Watershed<'_> {
+    fn index(&self, (row, col): Coord) -> Option<usize> {
+        self.map.get((row, col)).map(|_| row * self.map.width + col)
+    }
+
+    /// Where each sink drains, indexed by watershed
+    pub fn sinks(&self) -> Vec<Coord> {
+        self.sinks.iter().map(|&i| self.map.coord(i)).collect()
+    }
+
+    pub fn watershed_of(&self, coord: Coord) -> Option<usize> {
+        self.index(coord).map(|i| self.labels[i])
+    }
+
+    /// The sink that water from `coord` ends up in
+    pub fn drains_to(&self, coord: Coord) -> Option<Coord> {
+        self.watershed_of(coord).map(|w| self.map.coord(self.sinks[w]))
+    }
+
+    pub fn accumulation(&self, coord: Coord) -> Option<usize> {
+        self.index(coord).map(|i| self.accumulation[i])
+    }
+
+    /// How many locations drain into each sink, indexed by watershed
+    pub fn sizes(&self) -> Vec<usize> {
+        self.sinks.iter().map(|&i| self.accumulation[i]).collect()
+    }
+
+    /// For each pair of watersheds that touch, the lowest place water could cross from one to the other.
+    ///
+    /// Crossing between two neighbouring locations means climbing to the higher of them, which is where the
+    /// saddle is. Ties go to the first in row-major order.
+    pub fn saddles(&self) -> Vec<Saddle> {
+        let (width, h) = (self.map.width, &self.map.heights);
+        let mut lowest: BTreeMap<(usize, usize), (u8, usize)> = BTreeMap::new();
+        for i in 0..h.len() {
+            let right = (i % width + 1 < width).then_some(i + 1);
+            let below = (i + width < h.len()).then_some(i + width);
+            for j in [right, below].into_iter().flatten() {
+                let (a, b) = (self.labels[i], self.labels[j]);
+                if a == b {
+                    continue;
+                }
+                let pass = if h[j] > h[i] { (h[j], j) } else { (h[i], i) };
+                lowest.entry((a.min(b), a.max(b)))
+                    .and_modify(|best| *best = (*best).min(pass))
+                    .or_insert(pass);
+            }
+        }
+        lowest.into_iter()
+            .map(|(watersheds, (height, at))| Saddle { watersheds, at: self.map.coord(at), height })
+            .collect()
+    }
 
-        basin.insert(*coord);
-        for n in neighbors(coord) {
-            check.push_back(n);
+    /// The watersheds the locations of each ridge-bounded basin drain into, indexed by basin.
+    ///
+    /// Water never climbs, so it never leaves a basin over its rim, and each basin is made up of whole
+    /// watersheds apart from their ridge locations. Where every basin has a single low point, each entry has
+    /// exactly one watershed.
+    pub fn compare(&self, basins: &Basins) -> Vec<Vec<usize>> {
+        let mut found = vec![vec![]; basins.sizes().len()];
+        for (i, label) in basins.labels.iter().enumerate() {
+            if let Some(basin) = *label {
+                found[basin].push(self.labels[i]);
+            }
+        }
+        for watersheds in &mut found {
+            watersheds.sort_unstable();
+            watersheds.dedup();
         }
+        found
     }
-    basin
 }
 
-const RIDGE: &i32 = &9;
+fn parse_input(input: &str) -> Heightmap {
+    Heightmap::parse(input).unwrap_or_else(|e| panic!("{}", e))
+}
+
+pub fn part1(input: &str) -> String {
+    let map = parse_input(input);
+    map.low_points().into_iter()
+        .map(|p| map.get(p).unwrap() as u32 + 1)
+        .sum::<u32>()
+        .to_string()
+}
+
+pub fn part2(input: &str) -> String {
+    let map = parse_input(input);
+    let mut sizes = map.basins(RIDGE).sizes().to_vec();
+    sizes.sort_unstable_by(|a, b| b.cmp(a));
+    sizes.iter().take(3).product::<usize>().to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-pub fn part2(input: String) {
-    let height_map = parse_input(input);
-    let mut seen: HashSet<Coord> = HashSet::new();
+    const SAMPLE: &str = include_str!("../inputs/examples/day09_sample.txt");
 
-    let mut basin_sizes: Vec<usize> = Vec::new();
+    #[test]
+    fn basins() {
+        let map = Heightmap::parse(SAMPLE).unwrap();
+        assert_eq!(map.low_points(), vec![(0, 1), (0, 9), (2, 2), (4, 6)]);
+        let basins = map.basins(RIDGE);
+        assert_eq!(basins.sizes(), [3, 9, 14, 9]);
+        assert_eq!(basins.basin_of((0, 0)), Some(0));
+        assert_eq!(basins.basin_of((0, 2)), None);
+        assert_eq!(basins.basin_of((9, 9)), None);
+        assert_eq!(basins.members(0), vec![(0, 0), (0, 1), (1, 0)]);
+        assert_eq!(basins.rim(0), vec![(0, 2), (1, 1), (2, 0)]);
 
-    for (coord, value) in &height_map {
-        if seen.contains(coord) { continue; }
-        if value == RIDGE { continue; }
-        let basin = explore_basin(&height_map, coord);
-        basin_sizes.push(basin.len());
-        seen.extend(basin.iter());
+        // A lower ridge walls off the 8s too
+        assert_eq!(map.basins(8).sizes().iter().sum::<usize>(), 25);
     }
-    basin_sizes.sort_by(|a, b| b.partial_cmp(a).unwrap());
-    basin_sizes.truncate(3);
-    println!("{}", basin_sizes.into_iter().reduce(|a,b|a*b).unwrap());
-}
\ No newline at end of file
+
+    #[test]
+    fn watershed() {
+        let map = Heightmap::parse(SAMPLE).unwrap();
+        let flow = map.watershed();
+        assert_eq!(flow.sinks(), map.low_points());
+        assert_eq!(flow.sizes().iter().sum::<usize>(), 50);
+        assert_eq!(flow.drains_to((0, 0)), Some((0, 1)));
+        // Without ridges the watersheds take in the 9s the basins leave out
+        let basins = map.basins(RIDGE);
+        assert_eq!(flow.compare(&basins), vec![vec![0], vec![1], vec![2], vec![3]]);
+        for (w, &size) in flow.sizes().iter().enumerate() {
+            assert!(size >= basins.sizes()[w]);
+        }
+    }
+
+    #[test]
+    fn plateaus_and_saddles() {
+        let map = Heightmap::parse("2221").unwrap();
+        let flow = map.watershed();
+        assert_eq!(flow.sinks(), vec![(0, 3)]);
+        assert_eq!((0..4).map(|col| flow.accumulation((0, col)).unwrap()).collect::<Vec<_>>(), [1, 2, 3, 4]);
+
+        // A flat bottom is one sink
+        let map = Heightmap::parse("11\n11").unwrap();
+        assert_eq!(map.watershed().sinks(), vec![(0, 0)]);
+        assert_eq!(map.low_points(), vec![]);
+
+        let map = Heightmap::parse("131\n444").unwrap();
+        let flow = map.watershed();
+        assert_eq!(flow.sinks(), vec![(0, 0), (0, 2)]);
+        // The 3 is a tie and goes left, and takes the 4 below it along
+        assert_eq!(flow.drains_to((1, 1)), Some((0, 0)));
+        assert_eq!(flow.sizes(), [4, 2]);
+        assert_eq!(flow.saddles(), vec![Saddle { watersheds: (0, 1), at: (0, 1), height: 3 }]);
+    }
+
+    #[test]
+    fn parse_errors() {
+        assert_eq!(Heightmap::parse("123\n456\n").unwrap().get((1, 2)), Some(6));
+        assert_eq!(Heightmap::parse("123\n4x6").err(), Some(ParseError::InvalidHeight { row: 1, col: 1, found: 'x' }));
+        assert_eq!(Heightmap::parse("123\n45").err(), Some(ParseError::Ragged { row: 1 }));
+        assert_eq!(Heightmap::parse("1é3\n456").err(), Some(ParseError::InvalidHeight { row: 0, col: 1, found: 'é' }));
+        assert_eq!(Heightmap::parse("123\n4é").err(), Some(ParseError::InvalidHeight { row: 1, col: 1, found: 'é' }));
+    }
+}
@@ -1,92 +1,361 @@
-use crate::day10::State::{Complete, Corrupt, Incomplete};
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+use std::io::{self, BufRead};
 
-enum State {
+/// A pair of delimiters, with what it scores for a closer turning up where it doesn't belong and for a
+/// closer added to complete a line
+#[derive(Clone, Copy, Debug)]
+pub struct Pair {
+    pub open: char,
+    pub close: char,
+    pub corrupt_score: u64,
+    pub complete_score: u64,
+}
+
+/// What checking a line found
+#[derive(Debug, PartialEq)]
+pub enum Check {
     Complete,
-    Incomplete,
-    Corrupt,
+    /// Every chunk is well formed, but some are left open; `completion` closes them
+    Incomplete { completion: String },
+    /// A closer that doesn't match the innermost open chunk, at character `pos`. `expected` is `None` when
+    /// no chunk is open.
+    Corrupt { pos: usize, found: char, expected: Option<char> },
+    /// A character that isn't a delimiter of the language, at character `pos`
+    Invalid { pos: usize, found: char },
 }
 
-fn score_line(line: &str) -> (State, u64) {
-    let mut stack: Vec<char> = vec![];
+/// The fewest single-character insertions, deletions and substitutions that stop a line being corrupt
+#[derive(Debug, PartialEq)]
+pub struct Repair {
+    pub edits: usize,
+    /// The repaired line, which may still be incomplete
+    pub line: String,
+    pub completion: String,
+}
 
-    for ch in line.chars() {
-        match ch {
-            '(' => {
-                stack.push(ch);
-                0
-            }
-            ')' => match stack.pop() {
-                Some('(') => 0,
-                _ => return (Corrupt, 3),
-            }
-            '[' => {
-                stack.push(ch);
-                0
+/// A bracket language given by its delimiter pairs
+pub struct Language {
+    pairs: Vec<Pair>,
+    /// Completion scores are read as digits in this base
+    completion_base: u64,
+}
+
+impl Language {
+    pub fn new(pairs: &[Pair], completion_base: u64) -> Language {
+        Language { pairs: pairs.to_vec(), completion_base }
+    }
+
+    /// `()`, `[]`, `{}` and `<>`, scored as in the puzzle
+    pub fn standard() -> Language {
+        let pair = |open, close, corrupt_score, complete_score| Pair { open, close, corrupt_score, complete_score };
+        Language::new(&[
+            pair('(', ')', 3, 1),
+            pair('[', ']', 57, 2),
+            pair('{', '}', 1197, 3),
+            pair('<', '>', 25137, 4),
+        ], 5)
+    }
+
+    fn opening(&self, ch: char) -> Option<&Pair> {
+        self.pairs.iter().find(|p| p.open == ch)
+    }
+
+    fn closing(&self, ch: char) -> Option<&Pair> {
+        self.pairs.iter().find(|p| p.close == ch)
+    }
+
+    pub fn check(&self, line: &str) -> Check {
+        let mut open: Vec<&Pair> = vec![];
+        for (pos, ch) in line.chars().enumerate() {
+            if let Some(pair) = self.opening(ch) {
+                open.push(pair);
+            } else if self.closing(ch).is_some() {
+                match open.pop() {
+                    Some(pair) if pair.close == ch => {}
+                    innermost => return Check::Corrupt { pos, found: ch, expected: innermost.map(|p| p.close) },
+                }
+            } else {
+                return Check::Invalid { pos, found: ch };
             }
-            ']' => match stack.pop() {
-                Some('[') => 0,
-                _ => return (Corrupt, 57),
+        }
+        if open.is_empty() {
+            Check::Complete
+        } else {
+            Check::Incomplete { completion: open.iter().rev().map(|p| p.close).collect() }
+        }
+    }
+
+    /// What a corrupt line scores; anything else scores nothing
+    pub fn corruption_score(&self, check: &Check) -> u64 {
+        match check {
+            Check::Corrupt { found, .. } => self.closing(*found).unwrap().corrupt_score,
+            _ => 0,
+        }
+    }
+
+    pub fn completion_score(&self, completion: &str) -> u64 {
+        completion.chars().fold(0, |score, ch| {
+            score * self.completion_base + self.closing(ch).expect("Completions are made of closers").complete_score
+        })
+    }
+
+    /// Find the fewest edits that leave `line` a well-formed start of a line, then complete it.
+    ///
+    /// A well-formed start is balanced runs separated by openers that are still waiting for their closers.
+    /// `balanced[i][j]` is the fewest edits that balance `line[i..j]`, found by either dropping the first
+    /// character or pairing it with a later one (changing either or both to make them match). `prefix[j]`
+    /// extends the best well-formed start of `line[..i]` by a balanced run `line[i..j]`, or keeps
+    /// `line[j - 1]` as an opener, dropping it if it isn't one. Inserting a partner never beats dropping, so
+    /// insertions aren't tried. This takes cubic time in the length of the line.
+    pub fn repair(&self, line: &str) -> Repair {
+        let chars = line.chars().collect::<Vec<_>>();
+        let n = chars.len();
+        let mut balanced = vec![vec![0; n + 1]; n + 1];
+        for len in 1..=n {
+            for i in 0..=n - len {
+                let j = i + len;
+                let mut best = 1 + balanced[i + 1][j];
+                for k in i + 1..j {
+                    best = best.min(self.pairing(chars[i], chars[k]).0 + balanced[i + 1][k] + balanced[k + 1][j]);
+                }
+                balanced[i][j] = best;
             }
-            '{' => {
-                stack.push(ch);
-                0
+        }
+        let mut prefix = vec![0; n + 1];
+        for j in 1..=n {
+            let keep_or_drop = prefix[j - 1] + self.opening(chars[j - 1]).is_none() as usize;
+            prefix[j] = (0..j).map(|i| prefix[i] + balanced[i][j]).fold(keep_or_drop, usize::min);
+        }
+
+        // Walk back through the choices to rebuild the line
+        let mut pieces = vec![];
+        let mut j = n;
+        while j > 0 {
+            if let Some(i) = (0..j).find(|&i| prefix[i] + balanced[i][j] == prefix[j]) {
+                pieces.push(self.rebuild_balanced(&chars, &balanced, i, j));
+                j = i;
+            } else {
+                // An opener left waiting, or anything else dropped
+                let ch = chars[j - 1];
+                if self.opening(ch).is_some() {
+                    pieces.push(String::from(ch));
+                }
+                j -= 1;
             }
-            '}' => match stack.pop() {
-                Some('{') => 0,
-                _ => return (Corrupt, 1197),
+        }
+        let line = pieces.into_iter().rev().collect::<String>();
+        let completion = match self.check(&line) {
+            Check::Complete => String::new(),
+            Check::Incomplete { completion } => completion,
+            other => unreachable!("Repaired line is {:?}", other),
+        };
+        Repair { edits: prefix[n], line, completion }
+    }
+
+    /// The fewest substitutions that make `a` and `b` a matching pair, and the pair they become
+    fn pairing(&self, a: char, b: char) -> (usize, &Pair) {
+        self.pairs.iter()
+            .map(|p| ((p.open != a) as usize + (p.close != b) as usize, p))
+            .min_by_key(|&(cost, _)| cost)
+            .expect("A language needs at least one pair")
+    }
+
+    fn rebuild_balanced(&self, chars: &[char], balanced: &[Vec<usize>], i: usize, j: usize) -> String {
+        if i == j {
+            return String::new();
+        }
+        for k in i + 1..j {
+            let (cost, pair) = self.pairing(chars[i], chars[k]);
+            if cost + balanced[i + 1][k] + balanced[k + 1][j] == balanced[i][j] {
+                return format!("{}{}{}{}", pair.open, self.rebuild_balanced(chars, balanced, i + 1, k), pair.close,
+                               self.rebuild_balanced(chars, balanced, k + 1, j));
             }
-            '<' => {
-                stack.push(ch);
-                0
+        }
+        // Dropped
+        self.rebuild_balanced(chars, balanced, i + 1, j)
+    }
+}
+
+/// Counts over every line a `Checker` has seen
+#[derive(Debug, Default, PartialEq)]
+pub struct Summary {
+    pub complete: usize,
+    pub incomplete: usize,
+    pub corrupt: usize,
+    pub invalid: usize,
+    pub corruption_total: u64,
+}
+
+/// The median of a stream of numbers, kept as a max-heap of the lower half and a min-heap of the upper half.
+///
+/// With an even count this is the upper of the two middle values.
+#[derive(Default)]
+pub struct RunningMedian {
+    lower: BinaryHeap<u64>,
+    upper: BinaryHeap<Reverse<u64>>,
+}
+
+impl RunningMedian {
+    pub fn push(&mut self, value: u64) {
+        // Keep the upper half the same size as the lower or one bigger
+        if self.upper.peek().is_some_and(|&Reverse(min)| value < min) {
+            self.lower.push(value);
+        } else {
+            self.upper.push(Reverse(value));
+        }
+        if self.lower.len() > self.upper.len() {
+            self.upper.push(Reverse(self.lower.pop().unwrap()));
+        } else if self.upper.len() > self.lower.len() + 1 {
+            self.lower.push(self.upper.pop().unwrap().0);
+        }
+    }
+
+    pub fn median(&self) -> Option<u64> {
+        self.upper.peek().map(|&Reverse(median)| median)
+    }
+}
+
+/// Checks lines one at a time, keeping only running totals and the completion scores' median
+pub struct Checker<'a> {
+    language: &'a Language,
+    summary: Summary,
+    completions: RunningMedian,
+}
+
+impl<'a> Checker<'a> {
+    pub fn new(language: &'a Language) -> Checker<'a> {
+        Checker { language, summary: Summary::default(), completions: RunningMedian::default() }
+    }
+
+    pub fn push(&mut self, line: &str) -> Check {
+        let check = self.language.check(line);
+        match &check {
+            Check::Complete => self.summary.complete += 1,
+            Check::Incomplete { completion } => {
+                self.summary.incomplete += 1;
+                self.completions.push(self.language.completion_score(completion));
             }
-            '>' => match stack.pop() {
-                Some('<') => 0,
-                _ => return (Corrupt, 25137),
+            Check::Corrupt { .. } => {
+                self.summary.corrupt += 1;
+                self.summary.corruption_total += self.language.corruption_score(&check);
             }
-            _ => panic!("Didn't see {} coming", ch)
-        };
+            Check::Invalid { .. } => self.summary.invalid += 1,
+        }
+        check
+    }
+
+    /// Check every line from `input`, reusing one buffer so memory doesn't grow with the input
+    pub fn read(&mut self, input: &mut dyn BufRead) -> io::Result<()> {
+        let mut line = String::new();
+        while input.read_line(&mut line)? > 0 {
+            self.push(line.trim_end_matches(['\n', '\r']));
+            line.clear();
+        }
+        Ok(())
+    }
+
+    pub fn summary(&self) -> &Summary {
+        &self.summary
+    }
+
+    /// The median completion score of the incomplete lines
+    pub fn completion_median(&self) -> Option<u64> {
+        self.completions.median()
     }
-    if stack.is_empty() {
-        return (Complete, 0);
-    }
-    let mut score:u64 = 0;
-    while !stack.is_empty() {
-        score = score * 5 + match stack.pop() {
-            Some('(') => 1,
-            Some('[') => 2,
-            Some('{') => 3,
-            Some('<') => 4,
-            _ => panic!("Not an opening brace")
-        };
-    };
-    (Incomplete, score)
 }
 
-pub fn part1(input: String) {
-    let total: u64 = input.split("\n")
-        .map(|line| {
-            let (state, score) = score_line(line);
-            match state {
-                State::Complete => 0,
-                State::Incomplete => 0,
-                State::Corrupt => score,
-            }
-        })
-        .sum();
-    println!("{}", total);
+fn check_all<'a>(input: &mut dyn BufRead, language: &'a Language) -> Checker<'a> {
+    let mut checker = Checker::new(language);
+    checker.read(input).unwrap();
+    assert_eq!(checker.summary().invalid, 0, "Some lines have characters that aren't delimiters");
+    checker
 }
 
-pub fn part2(input: String) {
-    let mut scores: Vec<u64> = input.split("\n")
-        .map(|line| {
-            let (state, score) = score_line(line);
-            match state {
-                State::Complete => 0,
-                State::Incomplete => score,
-                State::Corrupt => 0,
-            }
-        }).collect();
-    scores.retain(|&score| score > 0);
-    scores.sort();
-    println!("{}", scores[scores.len()/2]);
-}
\ No newline at end of file
+pub fn part1(input: &mut dyn BufRead) -> String {
+    let language = Language::standard();
+    check_all(input, &language).summary().corruption_total.to_string()
+}
+
+pub fn part2(input: &mut dyn BufRead) -> String {
+    let language = Language::standard();
+    check_all(input, &language).completion_median().expect("No incomplete lines").to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gen::Rng;
+
+    #[test]
+    fn check() {
+        let language = Language::standard();
+        assert_eq!(language.check("{([(<{}[<>[]}>{[]{[(<()>"), Check::Corrupt { pos: 12, found: '}', expected: Some(']') });
+        assert_eq!(language.check("())"), Check::Corrupt { pos: 2, found: ')', expected: None });
+        assert_eq!(language.check("[(a)]"), Check::Invalid { pos: 2, found: 'a' });
+        let check = language.check("[({(<(())[]>[[{[]{<()<>>");
+        assert_eq!(check, Check::Incomplete { completion: String::from("}}]])})]") });
+        assert_eq!(language.completion_score("}}]])})]"), 288957);
+        assert_eq!(language.check("<([]){()}[{}]>"), Check::Complete);
+    }
+
+    #[test]
+    fn custom_language() {
+        let language = Language::new(&[
+            Pair { open: '«', close: '»', corrupt_score: 10, complete_score: 1 },
+            Pair { open: '(', close: ')', corrupt_score: 20, complete_score: 2 },
+        ], 3);
+        assert_eq!(language.check("«(»"), Check::Corrupt { pos: 2, found: '»', expected: Some(')') });
+        assert_eq!(language.corruption_score(&language.check("«(»")), 10);
+        assert_eq!(language.check("[]"), Check::Invalid { pos: 0, found: '[' });
+        assert_eq!(language.completion_score(")»"), 2 * 3 + 1);
+    }
+
+    #[test]
+    fn repair() {
+        let language = Language::standard();
+        let repair = |line| language.repair(line);
+        assert_eq!(repair("(]"), Repair { edits: 1, line: String::from("()"), completion: String::new() });
+        assert_eq!(repair("[(])").edits, 1);
+        // Lines that are only incomplete need no edits
+        assert_eq!(repair("[({"), Repair { edits: 0, line: String::from("[({"), completion: String::from("})]") });
+        assert_eq!(repair(")").edits, 1);
+
+        let corrupt = "{([(<{}[<>[]}>{[]{[(<()>";
+        let repaired = repair(corrupt);
+        assert_eq!(repaired.edits, 1);
+        assert!(matches!(language.check(&repaired.line), Check::Incomplete { .. }));
+    }
+
+    #[test]
+    fn streaming() {
+        let language = Language::standard();
+        let mut checker = Checker::new(&language);
+        checker.read(&mut include_str!("../inputs/examples/day10_sample.txt").as_bytes()).unwrap();
+        checker.push("()");
+        checker.push("(x");
+        assert_eq!(checker.summary(), &Summary {
+            complete: 1,
+            incomplete: 5,
+            corrupt: 5,
+            invalid: 1,
+            corruption_total: 26397,
+        });
+        assert_eq!(checker.completion_median(), Some(288957));
+    }
+
+    #[test]
+    fn running_median() {
+        let mut rng = Rng::new(10);
+        let mut median = RunningMedian::default();
+        let mut seen = vec![];
+        assert_eq!(median.median(), None);
+        for _ in 0..500 {
+            let value = rng.below(100) as u64;
+            median.push(value);
+            seen.push(value);
+            seen.sort();
+            assert_eq!(median.median(), Some(seen[seen.len() / 2]));
+        }
+    }
+}
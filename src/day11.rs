@@ -1,106 +1,222 @@
-use std::collections::HashSet;
+use std::collections::HashMap;
 use std::fmt;
 
-type Coord = (i32, i32);
+/// (row, column)
+pub type Coord = (usize, usize);
 
-struct OctoGrid {
-    total_flashes: i32,
-    grid: Vec<Vec<i32>>,
+/// Which octopuses a flash reaches
+#[derive(Clone, Copy, Debug)]
+pub enum Neighbourhood {
+    /// All eight surrounding octopuses
+    Moore,
+    /// Only the four orthogonal ones
+    VonNeumann,
 }
 
-const ADJACENT: [Coord; 8] = [
-    (-1, -1),
-    (-1, 0),
-    (-1, 1),
-    (0, -1),
-    (0, 1),
-    (1, -1),
-    (1, 0),
-    (1, 1),
-];
+impl Neighbourhood {
+    fn offsets(self) -> &'static [(isize, isize)] {
+        match self {
+            Neighbourhood::Moore => &[(-1, -1), (-1, 0), (-1, 1), (0, -1), (0, 1), (1, -1), (1, 0), (1, 1)],
+            Neighbourhood::VonNeumann => &[(-1, 0), (0, -1), (0, 1), (1, 0)],
+        }
+    }
+}
+
+/// How a grid's flashing plays out in the long run
+#[derive(Debug, PartialEq)]
+pub enum Synchronisation {
+    /// Every octopus flashed together on this step
+    Synced(usize),
+    /// The grid after step `start + period` is the same as after step `start`, and no step up to then had
+    /// every octopus flash. Stepping is deterministic, so it repeats those steps forever and never syncs.
+    Cycles { start: usize, period: usize },
+}
+
+#[derive(Clone)]
+pub struct OctoGrid {
+    width: usize,
+    height: usize,
+    /// Energy levels in row-major order
+    energy: Vec<u32>,
+    /// An octopus flashes once its energy goes above this
+    threshold: u32,
+    neighbourhood: Neighbourhood,
+    total_flashes: usize,
+}
 
 impl fmt::Display for OctoGrid {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        let text: String = self.grid.iter()
-            .map(|line| {
-                line.iter().map(|x| x.to_string()).collect::<Vec<String>>().join("")
-            }).collect::<Vec<String>>().join("\n");
-        write!(f, "{}", text)
+        let rows = self.energy.chunks(self.width)
+            .map(|row| row.iter().map(|x| x.to_string()).collect::<String>())
+            .collect::<Vec<_>>();
+        write!(f, "{}", rows.join("\n"))
     }
 }
 
 impl OctoGrid {
-    fn from_str(s: String) -> OctoGrid {
-        let grid: Vec<Vec<i32>> = s.trim().split("\n").map(|line| {
-            line.chars().map(|ch| ch.to_string().parse().unwrap()).collect()
-        }).collect();
-        OctoGrid { grid, total_flashes: 0 }
+    /// A grid of single-digit energy levels, flashing above 9 into the eight surrounding octopuses
+    pub fn parse(s: &str) -> OctoGrid {
+        let rows = s.lines().filter(|line| !line.trim().is_empty()).collect::<Vec<_>>();
+        let width = rows.first().map_or(0, |row| row.trim().len());
+        let energy = rows.iter()
+            .flat_map(|row| {
+                assert_eq!(row.trim().len(), width, "Every row of the grid must be the same width");
+                row.trim().chars().map(|ch| ch.to_digit(10).unwrap_or_else(|| panic!("Invalid energy {:?}", ch)))
+            })
+            .collect();
+        OctoGrid {
+            width,
+            height: rows.len(),
+            energy,
+            threshold: 9,
+            neighbourhood: Neighbourhood::Moore,
+            total_flashes: 0,
+        }
+    }
+
+    pub fn with_threshold(mut self, threshold: u32) -> OctoGrid {
+        self.threshold = threshold;
+        self
+    }
+
+    pub fn with_neighbourhood(mut self, neighbourhood: Neighbourhood) -> OctoGrid {
+        self.neighbourhood = neighbourhood;
+        self
+    }
+
+    pub fn size(&self) -> usize {
+        self.width * self.height
     }
 
-    fn size(&self) -> i32 {
-        self.grid.len().pow(2) as i32
+    pub fn total_flashes(&self) -> usize {
+        self.total_flashes
     }
 
-    fn step(&mut self) -> i32 {
-        let size = self.grid.len() as i32;
-        let mut flashed: HashSet<Coord> = HashSet::new();
-        // Increase all by 1
-        for row in self.grid.iter_mut() {
-            for value in row.iter_mut() {
-                *value += 1;
+    fn neighbours(&self, i: usize) -> impl Iterator<Item = usize> + '_ {
+        let (row, col) = (i / self.width, i % self.width);
+        self.neighbourhood.offsets().iter().filter_map(move |&(dr, dc)| {
+            let (r, c) = (row.checked_add_signed(dr)?, col.checked_add_signed(dc)?);
+            (r < self.height && c < self.width).then_some(r * self.width + c)
+        })
+    }
+
+    /// Advance one step and return the octopuses that flashed, in row-major order.
+    ///
+    /// Every octopus that goes over the threshold is put on a worklist as it does, so each flash is handled
+    /// exactly once without rescanning the grid.
+    pub fn step(&mut self) -> Vec<Coord> {
+        let mut flashed = vec![false; self.size()];
+        let mut worklist = vec![];
+        for (i, energy) in self.energy.iter_mut().enumerate() {
+            *energy += 1;
+            if *energy > self.threshold {
+                flashed[i] = true;
+                worklist.push(i);
             }
         }
-        // Repeatedly check flashes
-        loop {
-            let mut tens: HashSet<Coord> = HashSet::new();
-            for (i, row) in self.grid.iter().enumerate() {
-                for (j, &value) in row.iter().enumerate() {
-                    let coord = (i as i32, j as i32);
-                    if value > 9 && !flashed.contains(&coord) {
-                        tens.insert(coord);
-                        flashed.insert(coord);
-                    }
-                }
-            }
-            if tens.is_empty() { break; }
-            for (i, j) in tens {
-                for (di, dj) in ADJACENT {
-                    let (i2, j2) = (i + di, j + dj);
-                    if 0 <= i2 && i2 < size && 0 <= j2 && j2 < size {
-                        self.grid[i2 as usize][j2 as usize] += 1;
-                    }
+        while let Some(i) = worklist.pop() {
+            for n in self.neighbours(i).collect::<Vec<_>>() {
+                self.energy[n] += 1;
+                if self.energy[n] > self.threshold && !flashed[n] {
+                    flashed[n] = true;
+                    worklist.push(n);
                 }
             }
         }
-        // Reset all flashed octopuses to 0
-        for (i, j) in &flashed {
-            self.grid[*i as usize][*j as usize] = 0;
+
+        let flashes = (0..self.size())
+            .filter(|&i| flashed[i])
+            .map(|i| {
+                self.energy[i] = 0;
+                (i / self.width, i % self.width)
+            })
+            .collect::<Vec<_>>();
+        self.total_flashes += flashes.len();
+        flashes
+    }
+
+    /// Step until every octopus flashes at once, or until the grid repeats a state it has been in before.
+    ///
+    /// Energy never stays above the threshold, so there are finitely many states and one of the two always
+    /// happens. Steps are counted from the grid's current state as step 0.
+    ///
+    /// Panics on an empty grid, where there are no octopuses to flash together.
+    pub fn synchronise(&mut self) -> Synchronisation {
+        assert!(self.size() > 0, "An empty grid can't synchronise");
+        let mut seen = HashMap::from([(self.energy.clone(), 0)]);
+        for step in 1.. {
+            if self.step().len() == self.size() {
+                return Synchronisation::Synced(step);
+            }
+            if let Some(start) = seen.insert(self.energy.clone(), step) {
+                return Synchronisation::Cycles { start, period: step - start };
+            }
         }
-        let num_flashes = flashed.len() as i32;
-        self.total_flashes += num_flashes;
-        num_flashes
+        unreachable!()
     }
 }
 
-pub fn part1(input: String) {
-    let mut octos = OctoGrid::from_str(input);
+pub fn part1(input: &str) -> String {
+    let mut octos = OctoGrid::parse(input);
 
     for _ in 0..100 {
         octos.step();
     }
-    println!("{}", octos.total_flashes);
+    octos.total_flashes().to_string()
 }
 
-pub fn part2(input: String) {
-    let mut octos = OctoGrid::from_str(input);
+pub fn part2(input: &str) -> String {
+    let mut octos = OctoGrid::parse(input);
 
-    let mut steps = 0;
-    loop {
-        let num_flashes = octos.step();
-        steps += 1;
-        if num_flashes == octos.size() {
-            println!("{}", steps);
-            break;
+    match octos.synchronise() {
+        Synchronisation::Synced(step) => step.to_string(),
+        Synchronisation::Cycles { start, period } => {
+            panic!("The octopuses never sync, repeating every {} steps from step {}", period, start)
         }
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cascade() {
+        let mut octos = OctoGrid::parse("11111\n19991\n19191\n19991\n11111");
+        assert_eq!(octos.step().len(), 9);
+        assert_eq!(octos.to_string(), "34543\n40004\n50005\n40004\n34543");
+        assert_eq!(octos.step(), vec![]);
+        assert_eq!(octos.to_string(), "45654\n51115\n61116\n51115\n45654");
+        assert_eq!(octos.total_flashes(), 9);
+    }
+
+    #[test]
+    fn configuration() {
+        let mut moore = OctoGrid::parse("191\n111");
+        assert_eq!(moore.step(), vec![(0, 1)]);
+        assert_eq!(moore.to_string(), "303\n333");
+
+        let mut von_neumann = OctoGrid::parse("191\n111").with_neighbourhood(Neighbourhood::VonNeumann);
+        assert_eq!(von_neumann.step(), vec![(0, 1)]);
+        assert_eq!(von_neumann.to_string(), "303\n232");
+
+        let mut low = OctoGrid::parse("000").with_threshold(1);
+        assert_eq!(low.step(), vec![]);
+        assert_eq!(low.step(), vec![(0, 0), (0, 1), (0, 2)]);
+        assert_eq!(low.to_string(), "000");
+    }
+
+    #[test]
+    fn synchronisation() {
+        assert_eq!(OctoGrid::parse("55").synchronise(), Synchronisation::Synced(5));
+        // The 2 always flashes a step before the 0s, so it never flashes with them
+        assert_eq!(OctoGrid::parse("002").synchronise(), Synchronisation::Cycles { start: 0, period: 9 });
+        assert_eq!(OctoGrid::parse("013").synchronise(), Synchronisation::Cycles { start: 16, period: 9 });
+    }
+
+    #[test]
+    #[should_panic(expected = "An empty grid can't synchronise")]
+    fn synchronise_empty() {
+        OctoGrid::parse("\n").synchronise();
+    }
+}
@@ -1,82 +1,391 @@
-use std::collections::{HashMap, HashSet, VecDeque};
-use std::vec;
+use std::collections::HashMap;
+use std::hash::Hash;
 
-enum Size {
-    Big,
-    Small,
+/// A cave system with every cave interned to an index.
+///
+/// Small caves other than `start` and `end` also get a bit index of their own, so a set of them fits in a
+/// `u64`.
+pub struct CaveGraph {
+    names: Vec<String>,
+    ids: HashMap<String, usize>,
+    /// The bit index of each cave, if it's small
+    small: Vec<Option<usize>>,
+    small_count: usize,
+    tunnels: Vec<(usize, usize)>,
+    /// Where you can go from each cave, sorted by name; nothing goes back into `start`
+    exits: Vec<Vec<usize>>,
+    start: usize,
+    end: usize,
 }
 
-use Size::*;
-
-fn size(cave: &str) -> Size {
-    if cave.to_uppercase() == cave { Big } else { Small }
+fn is_big(name: &str) -> bool {
+    name.chars().all(|ch| ch.is_ascii_uppercase())
 }
 
+impl CaveGraph {
+    pub fn parse(input: &str) -> CaveGraph {
+        let mut names = vec![];
+        let mut ids = HashMap::new();
+        let mut edges = vec![];
+        for line in input.lines().filter(|line| !line.trim().is_empty()) {
+            let (a, b) = line.trim().split_once("-").unwrap_or_else(|| panic!("Not a tunnel: {}", line));
+            assert!(!(is_big(a) && is_big(b)), "Big caves {} and {} are joined, so paths never end", a, b);
+            let mut id = |name: &str| *ids.entry(name.to_string()).or_insert_with(|| {
+                names.push(name.to_string());
+                names.len() - 1
+            });
+            edges.push((id(a), id(b)));
+        }
+        let start = *ids.get("start").expect("No start cave");
+        let end = *ids.get("end").expect("No end cave");
 
-pub fn part1(input: String) {
-    let mut map: HashMap<&str, HashSet<&str>> = HashMap::new();
-
-    for line in input.lines() {
-        let (a, b) = line.split_once("-").unwrap();
-        if a != "end" {
-            map.entry(a).or_insert(HashSet::new()).insert(b);
+        let mut exits = vec![vec![]; names.len()];
+        for &(a, b) in &edges {
+            if b != start && a != end {
+                exits[a].push(b);
+            }
+            if a != start && b != end {
+                exits[b].push(a);
+            }
         }
-        if a != "start" {
-            map.entry(b).or_insert(HashSet::new()).insert(a);
+        for cave_exits in &mut exits {
+            cave_exits.sort_by(|&a, &b| names[a].cmp(&names[b]));
         }
+        let mut small_count = 0;
+        let small = (0..names.len())
+            .map(|id| {
+                (id != start && id != end && !is_big(&names[id])).then(|| {
+                    small_count += 1;
+                    small_count - 1
+                })
+            })
+            .collect();
+        assert!(small_count <= 64, "At most 64 small caves are supported");
+        CaveGraph { names, ids, small, small_count, tunnels: edges, exits, start, end }
+    }
+
+    pub fn id(&self, name: &str) -> Option<usize> {
+        self.ids.get(name).copied()
+    }
+
+    pub fn name(&self, id: usize) -> &str {
+        &self.names[id]
     }
 
-    let mut total_paths = 0;
-    let mut paths: VecDeque<Vec<&str>> = VecDeque::from(vec![vec!["start"]]);
-    while !paths.is_empty() {
-        let path = paths.pop_front().unwrap();
-        for &next in map.get(path.last().unwrap()).unwrap() {
-            if path.iter().filter(|&value| *value == next).count() == 1 && matches!(size(next), Small) {
-                // println!("Cannot revisit {}", next);
-                continue;
+    /// Count every path from `start` to `end` that `policy` allows.
+    ///
+    /// The number of ways on from a cave depends only on the cave and the policy's state, so each pair is
+    /// only worked out once.
+    pub fn count_paths<P: RevisitPolicy>(&self, policy: &P) -> u64 {
+        let mut memo = HashMap::new();
+        self.count_from(self.start, policy.initial(), policy, &mut memo)
+    }
+
+    /// The first `limit` paths `policy` allows, in lexicographic order of their cave names
+    pub fn paths<P: RevisitPolicy>(&self, policy: &P, limit: usize) -> Vec<Vec<&str>> {
+        let mut paths = vec![];
+        self.walk(&mut vec![self.start], policy.initial(), policy, limit, &mut paths);
+        paths
+    }
+
+    /// Depth first, trying exits in name order, which finds paths in lexicographic order as no path is a
+    /// prefix of another
+    fn walk<'a, P: RevisitPolicy>(
+        &'a self,
+        path: &mut Vec<usize>,
+        state: P::State,
+        policy: &P,
+        limit: usize,
+        paths: &mut Vec<Vec<&'a str>>,
+    ) {
+        let cave = *path.last().unwrap();
+        if cave == self.end {
+            paths.push(path.iter().map(|&id| self.name(id)).collect());
+            return;
+        }
+        for &next in &self.exits[cave] {
+            if paths.len() == limit {
+                return;
             }
-            let mut new_path = path.clone();
-            new_path.push(next);
-            if next == "end" {
-                total_paths += 1;
+            if let Some(next_state) = self.step(&state, next, policy) {
+                path.push(next);
+                self.walk(path, next_state, policy, limit, paths);
+                path.pop();
+            }
+        }
+    }
+
+    /// A path chosen uniformly at random from those `policy` allows, or `None` if there are none.
+    ///
+    /// Each exit is taken with probability proportional to the number of paths through it. `below(n)` is the
+    /// source of randomness, and should give a uniformly random number less than `n`.
+    pub fn sample_path<P: RevisitPolicy>(&self, policy: &P, mut below: impl FnMut(u64) -> u64) -> Option<Vec<&str>> {
+        let mut memo = HashMap::new();
+        let mut state = policy.initial();
+        if self.count_from(self.start, state.clone(), policy, &mut memo) == 0 {
+            return None;
+        }
+        let mut path = vec![self.name(self.start)];
+        let mut cave = self.start;
+        while cave != self.end {
+            let options = self.exits[cave].iter()
+                .filter_map(|&next| {
+                    let next_state = self.step(&state, next, policy)?;
+                    let count = self.count_from(next, next_state.clone(), policy, &mut memo);
+                    Some((next, next_state, count))
+                })
+                .collect::<Vec<_>>();
+            let mut pick = below(options.iter().map(|&(_, _, count)| count).sum::<u64>());
+            let (next, next_state, _) = options.into_iter()
+                .find(|&(_, _, count)| {
+                    let here = pick < count;
+                    pick = pick.saturating_sub(count);
+                    here
+                })
+                .unwrap();
+            path.push(self.name(next));
+            cave = next;
+            state = next_state;
+        }
+        Some(path)
+    }
+
+    /// The state after going into `next`, or `None` if `policy` doesn't allow it
+    fn step<P: RevisitPolicy>(&self, state: &P::State, next: usize, policy: &P) -> Option<P::State> {
+        match self.small[next] {
+            Some(bit) => policy.enter(state, bit),
+            None => Some(state.clone()),
+        }
+    }
+
+    /// The cave system in Graphviz DOT. Big caves are boxes, small caves ellipses, and `start` and `end` are
+    /// double circles.
+    pub fn to_dot(&self) -> String {
+        let mut dot = String::from("graph caves {\n");
+        for (id, name) in self.names.iter().enumerate() {
+            let shape = if id == self.start || id == self.end {
+                "doublecircle"
+            } else if self.small[id].is_none() {
+                "box, style=filled, fillcolor=lightgrey"
             } else {
-                paths.push_back(new_path);
+                "ellipse"
+            };
+            dot += &format!("    \"{}\" [shape={}];\n", name, shape);
+        }
+        for &(a, b) in &self.tunnels {
+            dot += &format!("    \"{}\" -- \"{}\";\n", self.names[a], self.names[b]);
+        }
+        dot + "}\n"
+    }
+
+    fn count_from<P: RevisitPolicy>(
+        &self,
+        cave: usize,
+        state: P::State,
+        policy: &P,
+        memo: &mut HashMap<(usize, P::State), u64>,
+    ) -> u64 {
+        if cave == self.end {
+            return 1;
+        }
+        if let Some(&count) = memo.get(&(cave, state.clone())) {
+            return count;
+        }
+        let mut count = 0;
+        for &next in &self.exits[cave] {
+            if let Some(next_state) = self.step(&state, next, policy) {
+                count += self.count_from(next, next_state, policy, memo);
             }
         }
+        memo.insert((cave, state), count);
+        count
     }
-    println!("There are {} paths", total_paths);
 }
 
-pub fn part2(input: String) {
-    let mut map: HashMap<&str, Vec<&str>> = HashMap::new();
+/// Which small caves a path may go back into.
+///
+/// Small caves are given by their bit index in the graph; `start` and `end` are never offered, as a path
+/// can't go back to `start` and stops at `end`.
+pub trait RevisitPolicy {
+    /// What the policy needs to remember about the path so far
+    type State: Clone + Eq + Hash;
+
+    fn initial(&self) -> Self::State;
+
+    /// The state after entering small cave `cave`, or `None` if the path may not go there
+    fn enter(&self, state: &Self::State, cave: usize) -> Option<Self::State>;
+}
+
+/// Up to this many small caves may be visited twice, and the rest at most once. `Revisits(0)` is part 1's
+/// rule and `Revisits(1)` part 2's.
+pub struct Revisits(pub usize);
+
+impl RevisitPolicy for Revisits {
+    /// The small caves visited so far, and those visited twice
+    type State = (u64, u64);
 
-    for line in input.lines() {
-        let (a, b) = line.split_once("-").unwrap();
-        if a != "end" && b != "start" {
-            map.entry(a).or_insert(Vec::new()).push(b);
+    fn initial(&self) -> (u64, u64) {
+        (0, 0)
+    }
+
+    fn enter(&self, &(visited, twice): &(u64, u64), cave: usize) -> Option<(u64, u64)> {
+        let bit = 1 << cave;
+        if visited & bit == 0 {
+            Some((visited | bit, twice))
+        } else if twice & bit == 0 && (twice.count_ones() as usize) < self.0 {
+            Some((visited, twice | bit))
+        } else {
+            None
         }
-        if a != "start" && b != "end" {
-            map.entry(b).or_insert(Vec::new()).push(a);
+    }
+}
+
+/// A limit on visits to each small cave
+pub struct PerCave {
+    limits: Vec<u8>,
+}
+
+impl PerCave {
+    /// Every small cave may be visited `default` times, apart from those in `limits`
+    pub fn new(graph: &CaveGraph, default: u8, limits: &[(&str, u8)]) -> PerCave {
+        let mut per_cave = vec![default; graph.small_count];
+        for &(name, limit) in limits {
+            let id = graph.id(name).unwrap_or_else(|| panic!("No cave called {}", name));
+            let bit = graph.small[id].unwrap_or_else(|| panic!("{} isn't a small cave", name));
+            per_cave[bit] = limit;
         }
+        PerCave { limits: per_cave }
+    }
+}
+
+impl RevisitPolicy for PerCave {
+    /// Visits to each small cave so far
+    type State = Vec<u8>;
+
+    fn initial(&self) -> Vec<u8> {
+        vec![0; self.limits.len()]
     }
 
-    let mut total_paths = 0;
-    let mut paths: VecDeque<(Vec<&str>, bool)> = VecDeque::from(vec![(vec!["start"], false)]);
-    while !paths.is_empty() {
-        let (path, has_backtrack) = paths.pop_front().unwrap();
-        for &next in map.get(path.last().unwrap()).unwrap() {
-            let mut backtrack = has_backtrack;
-            if matches!(size(next), Small) && path.contains(&next) {
-                if backtrack { continue; } else { backtrack = true; }
+    fn enter(&self, visits: &Vec<u8>, cave: usize) -> Option<Vec<u8>> {
+        (visits[cave] < self.limits[cave]).then(|| {
+            let mut visits = visits.clone();
+            visits[cave] += 1;
+            visits
+        })
+    }
+}
+
+pub fn part1(input: &str) -> String {
+    CaveGraph::parse(input).count_paths(&Revisits(0)).to_string()
+}
+
+pub fn part2(input: &str) -> String {
+    CaveGraph::parse(input).count_paths(&Revisits(1)).to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::gen::Rng;
+
+    use super::*;
+
+    const SMALL: &str = include_str!("../inputs/examples/day12_small.txt");
+    const MEDIUM: &str = include_str!("../inputs/examples/day12_medium.txt");
+
+    /// Walk every path, counting visits to each small cave
+    fn brute_force(graph: &CaveGraph, allowed: &dyn Fn(&HashMap<usize, u8>) -> bool) -> u64 {
+        type Allowed<'a> = &'a dyn Fn(&HashMap<usize, u8>) -> bool;
+
+        fn walk(graph: &CaveGraph, cave: usize, visits: &mut HashMap<usize, u8>, allowed: Allowed) -> u64 {
+            if cave == graph.end {
+                return 1;
             }
-            if next == "end" {
-                total_paths += 1;
-            } else {
-                let mut new_path = path.clone();
-                new_path.push(next);
-                paths.push_back((new_path, backtrack));
+            let mut count = 0;
+            for &next in &graph.exits[cave] {
+                if let Some(bit) = graph.small[next] {
+                    *visits.entry(bit).or_default() += 1;
+                    if allowed(visits) {
+                        count += walk(graph, next, visits, allowed);
+                    }
+                    *visits.get_mut(&bit).unwrap() -= 1;
+                } else {
+                    count += walk(graph, next, visits, allowed);
+                }
+            }
+            count
+        }
+        walk(graph, graph.start, &mut HashMap::new(), allowed)
+    }
+
+    #[test]
+    fn policies_match_brute_force() {
+        for (input, favourite) in [(SMALL, "b"), (MEDIUM, "dc")] {
+            let graph = CaveGraph::parse(input);
+            for k in 0..4 {
+                let expected = brute_force(&graph, &|visits| {
+                    visits.values().all(|&v| v <= 2) && visits.values().filter(|&&v| v == 2).count() <= k
+                });
+                assert_eq!(graph.count_paths(&Revisits(k)), expected, "{} revisits", k);
             }
+            let bit = graph.small[graph.id(favourite).unwrap()].unwrap();
+            let per_cave = PerCave::new(&graph, 1, &[(favourite, 3)]);
+            let expected = brute_force(&graph, &|visits| {
+                visits.iter().all(|(&cave, &v)| v <= if cave == bit { 3 } else { 1 })
+            });
+            assert_eq!(graph.count_paths(&per_cave), expected);
         }
     }
-    println!("There are {} paths", total_paths);
+
+    #[test]
+    fn interning() {
+        let graph = CaveGraph::parse(SMALL);
+        assert_eq!(graph.id("start").map(|id| graph.name(id)), Some("start"));
+        assert_eq!(graph.id("A").map(|id| graph.small[id]), Some(None));
+        assert_eq!(graph.small_count, 3);
+        assert_eq!(graph.id("missing"), None);
+    }
+
+    #[test]
+    fn listing_and_sampling() {
+        let graph = CaveGraph::parse(SMALL);
+        let paths = graph.paths(&Revisits(0), usize::MAX).iter().map(|p| p.join(",")).collect::<Vec<_>>();
+        assert_eq!(paths, [
+            "start,A,b,A,c,A,end",
+            "start,A,b,A,end",
+            "start,A,b,end",
+            "start,A,c,A,b,A,end",
+            "start,A,c,A,b,end",
+            "start,A,c,A,end",
+            "start,A,end",
+            "start,b,A,c,A,end",
+            "start,b,A,end",
+            "start,b,end",
+        ]);
+        assert_eq!(graph.paths(&Revisits(1), 2).len(), 2);
+
+        let mut rng = Rng::new(12);
+        let mut below = |n| rng.next_u64() % n;
+        let mut seen = HashMap::new();
+        for _ in 0..1000 {
+            let path = graph.sample_path(&Revisits(0), &mut below).unwrap().join(",");
+            *seen.entry(path).or_insert(0) += 1;
+        }
+        assert_eq!(seen.len(), 10);
+        assert!(seen.keys().all(|p| paths.contains(p)));
+        assert!(seen.values().all(|&n| n > 50), "{:?}", seen);
+
+        let stuck = CaveGraph::parse("start-a\na-end\nb-end");
+        assert_eq!(stuck.sample_path(&PerCave::new(&stuck, 0, &[]), &mut below), None);
+    }
+
+    #[test]
+    fn dot() {
+        let dot = CaveGraph::parse(SMALL).to_dot();
+        assert!(dot.starts_with("graph caves {\n"));
+        assert!(dot.contains("    \"A\" [shape=box, style=filled, fillcolor=lightgrey];\n"));
+        assert!(dot.contains("    \"c\" [shape=ellipse];\n"));
+        assert!(dot.contains("    \"end\" [shape=doublecircle];\n"));
+        assert!(dot.contains("    \"b\" -- \"end\";\n"));
+        assert_eq!(dot.matches(" -- ").count(), 7);
+    }
 }
@@ -1,79 +1,236 @@
 use std::collections::HashSet;
-use regex::Regex;
+use std::fmt;
+use std::sync::OnceLock;
 
-#[derive(Debug)]
-#[derive(Eq, PartialEq, Hash)]
-struct Coord {
-    x: i32,
-    y: i32,
-}
+use regex::Regex;
+use tracing::{debug, debug_span};
 
+/// (x, y), with y growing downwards
+pub type Dot = (i64, i64);
 
 const DOT: char = '█';
 const EMPTY: char = ' ';
 
-impl Coord {
-    fn fold_over(&self, dim: &str, val: i32) -> Coord {
-        let new_x = match dim {
-            "x" => {
-                val - (self.x - val).abs()
-            }
-            "y" => self.x,
-            _ => panic!(),
-        };
-        let new_y = match dim {
-            "x" => self.y,
-            "y" => {
-                val - (self.y - val).abs()
-            }
-            _ => panic!(),
-        };
-        Coord { x: new_x, y: new_y }
+/// A line to fold along. Whatever is past the line, on the side with larger coordinates, is folded back onto
+/// the other side; the line needn't be in the middle of the sheet.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Fold {
+    /// `x=n`, folding the right part left
+    X(i64),
+    /// `y=n`, folding the bottom part up
+    Y(i64),
+    /// `x-y=n`, folding the part above and right of the diagonal down and left
+    Diagonal(i64),
+    /// `x+y=n`, folding the part below and right of the diagonal up and left
+    AntiDiagonal(i64),
+}
+
+impl Fold {
+    /// Parses `fold along x=5`, `fold along y=7`, `fold along x-y=3` or `fold along x+y=10`
+    pub fn parse(line: &str) -> Option<Fold> {
+        // Compiled once and shared, as this runs for every fold line
+        static FOLD: OnceLock<Regex> = OnceLock::new();
+        let r = FOLD.get_or_init(|| Regex::new(r"^fold along (x|y|x-y|x\+y)=(-?\d+)$").unwrap());
+        let caps = r.captures(line.trim())?;
+        let value = caps[2].parse::<i64>().ok()?;
+        Some(match &caps[1] {
+            "x" => Fold::X(value),
+            "y" => Fold::Y(value),
+            "x-y" => Fold::Diagonal(value),
+            _ => Fold::AntiDiagonal(value),
+        })
+    }
+
+    fn apply(self, (x, y): Dot) -> Dot {
+        match self {
+            Fold::X(line) if x > line => (2 * line - x, y),
+            Fold::Y(line) if y > line => (x, 2 * line - y),
+            Fold::Diagonal(line) if x - y > line => (y + line, x - line),
+            Fold::AntiDiagonal(line) if x + y > line => (line - y, line - x),
+            _ => (x, y),
+        }
     }
 }
 
+/// A sheet of transparent paper with dots on it
+#[derive(Clone, Debug, PartialEq)]
+pub struct Sheet {
+    dots: HashSet<Dot>,
+}
 
-pub fn solve(input: String, max_folds: usize, print_result: bool) {
-    let (coord_input, command_input) = input.split_once("\n\n").unwrap();
-    let mut coords: HashSet<Coord> = coord_input.lines()
-        .map(|line| {
-            let (a, b) = line.split_once(",").unwrap();
-            let x = a.parse::<i32>().unwrap();
-            let y = b.parse::<i32>().unwrap();
-            Coord { x, y }
-        })
-        .collect();
+impl Sheet {
+    pub fn new(dots: impl IntoIterator<Item = Dot>) -> Sheet {
+        Sheet { dots: dots.into_iter().collect() }
+    }
+
+    pub fn dots(&self) -> &HashSet<Dot> {
+        &self.dots
+    }
+
+    pub fn fold(&mut self, fold: Fold) {
+        self.dots = self.dots.iter().map(|&dot| fold.apply(dot)).collect();
+    }
 
-    let r = Regex::new(r"fold along (.)=(\d+)").unwrap();
-    for (i, command) in command_input.lines().enumerate() {
-        if i >= max_folds { break; }
-        let caps = r.captures(command).unwrap();
-        let dim = caps.get(1).unwrap().as_str();
-        let value = caps.get(2).unwrap().as_str().parse::<i32>().unwrap();
-        coords = coords.iter().map(|c| c.fold_over(dim, value)).collect();
+    /// The top-left corner of the dots and the bottom-right, inclusive
+    fn bounds(&self) -> Option<(Dot, Dot)> {
+        let xs = self.dots.iter().map(|&(x, _)| x);
+        let ys = self.dots.iter().map(|&(_, y)| y);
+        Some(((xs.clone().min()?, ys.clone().min()?), (xs.max()?, ys.max()?)))
     }
-    println!("{}", coords.len());
-
-    if print_result {
-        let x_max = coords.iter().map(|c| c.x).max().unwrap();
-        let y_max = coords.iter().map(|c| c.y).max().unwrap();
-        for y in 0..y_max + 1 {
-            for x in 0..x_max + 1 {
-                if coords.contains(&Coord { x, y }) {
-                    print!("{}", DOT);
-                } else {
-                    print!("{}", EMPTY);
-                }
-            }
-            println!();
+
+    /// Read the dots as capital letters in the 4×6 font, one letter every five columns.
+    ///
+    /// Returns `None` unless every letter is recognised.
+    pub fn read(&self) -> Option<String> {
+        let ((left, top), (right, bottom)) = self.bounds()?;
+        if bottom - top + 1 != 6 {
+            return None;
         }
+        // Some letters, like I, leave their first columns empty
+        (0..4).find_map(|shift| self.read_letters(left - shift, right, top))
+    }
+
+    fn read_letters(&self, left: i64, right: i64, top: i64) -> Option<String> {
+        let bottom = top + 5;
+        (0..(right - left) / 5 + 1)
+            .map(|i| {
+                let cell_left = left + 5 * i;
+                let gap_is_empty = (top..=bottom).all(|y| !self.dots.contains(&(cell_left + 4, y)));
+                let glyph = (top..=bottom)
+                    .map(|y| (cell_left..cell_left + 4).map(move |x| if self.dots.contains(&(x, y)) { '#' } else { '.' }))
+                    .map(String::from_iter)
+                    .collect::<Vec<_>>();
+                let letter = FONT.iter().find(|(_, rows)| rows.iter().eq(glyph.iter()))?.0;
+                gap_is_empty.then_some(letter)
+            })
+            .collect()
     }
 }
 
-pub fn part1(input: String) {
-    solve(input, 1, false);
+/// Draws the dots with `█`, from the top-left dot
+impl fmt::Display for Sheet {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let Some(((left, top), (right, bottom))) = self.bounds() else { return Ok(()) };
+        let rows = (top..=bottom)
+            .map(|y| {
+                (left..=right)
+                    .map(|x| if self.dots.contains(&(x, y)) { DOT } else { EMPTY })
+                    .collect::<String>()
+            })
+            .collect::<Vec<_>>();
+        write!(f, "{}", rows.join("\n"))
+    }
 }
 
-pub fn part2(input: String) {
-    solve(input, usize::MAX, true);
-}
\ No newline at end of file
+/// The letters of the 4×6 font that are known
+const FONT: [(char, [&str; 6]); 17] = [
+    ('A', [".##.", "#..#", "#..#", "####", "#..#", "#..#"]),
+    ('B', ["###.", "#..#", "###.", "#..#", "#..#", "###."]),
+    ('C', [".##.", "#..#", "#...", "#...", "#..#", ".##."]),
+    ('E', ["####", "#...", "###.", "#...", "#...", "####"]),
+    ('F', ["####", "#...", "###.", "#...", "#...", "#..."]),
+    ('G', [".##.", "#..#", "#...", "#.##", "#..#", ".###"]),
+    ('H', ["#..#", "#..#", "####", "#..#", "#..#", "#..#"]),
+    ('I', [".###", "..#.", "..#.", "..#.", "..#.", ".###"]),
+    ('J', ["..##", "...#", "...#", "...#", "#..#", ".##."]),
+    ('K', ["#..#", "#.#.", "##..", "#.#.", "#.#.", "#..#"]),
+    ('L', ["#...", "#...", "#...", "#...", "#...", "####"]),
+    ('O', [".##.", "#..#", "#..#", "#..#", "#..#", ".##."]),
+    ('P', ["###.", "#..#", "#..#", "###.", "#...", "#..."]),
+    ('R', ["###.", "#..#", "#..#", "###.", "#.#.", "#..#"]),
+    ('S', [".###", "#...", "#...", ".##.", "...#", "###."]),
+    ('U', ["#..#", "#..#", "#..#", "#..#", "#..#", ".##."]),
+    ('Z', ["####", "...#", "..#.", ".#..", "#...", "####"]),
+];
+
+fn parse_input(input: &str) -> (Sheet, Vec<Fold>) {
+    let (dot_input, fold_input) = input.split_once("\n\n").unwrap();
+    let sheet = Sheet::new(dot_input.lines().map(|line| {
+        let (x, y) = line.split_once(",").unwrap();
+        (x.trim().parse::<i64>().unwrap(), y.trim().parse::<i64>().unwrap())
+    }));
+    let folds = fold_input.lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| Fold::parse(line).unwrap_or_else(|| panic!("Not a fold: {}", line)))
+        .collect();
+    (sheet, folds)
+}
+
+/// Make the first `max_folds` folds
+pub fn solve(input: &str, max_folds: usize) -> Sheet {
+    let (mut sheet, folds) = parse_input(input);
+    let _span = debug_span!("fold", dots = sheet.dots().len()).entered();
+    for (i, &fold) in folds.iter().take(max_folds).enumerate() {
+        sheet.fold(fold);
+        debug!(fold = i + 1, ?fold, dots = sheet.dots().len(), "Folded");
+    }
+    sheet
+}
+
+pub fn part1(input: &str) -> String {
+    solve(input, 1).dots().len().to_string()
+}
+
+/// The letters on the folded sheet, or a drawing of it if they can't all be read
+pub fn part2(input: &str) -> String {
+    let sheet = solve(input, usize::MAX);
+    sheet.read().unwrap_or_else(|| sheet.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sheet(rows: &[&str]) -> Sheet {
+        Sheet::new(rows.iter().enumerate().flat_map(|(y, row)| {
+            row.chars().enumerate().filter(|&(_, ch)| ch == '#').map(move |(x, _)| (x as i64, y as i64))
+        }))
+    }
+
+    #[test]
+    fn ocr() {
+        let hi = sheet(&[
+            "#..#..###.",
+            "#..#...#..",
+            "####...#..",
+            "#..#...#..",
+            "#..#...#..",
+            "#..#..###.",
+        ]);
+        assert_eq!(hi.read(), Some(String::from("HI")));
+        // Two folds move the letters to negative coordinates without mirroring them
+        let mut moved = hi.clone();
+        moved.fold(Fold::X(-2));
+        moved.fold(Fold::X(-20));
+        assert_eq!(moved.read(), Some(String::from("HI")));
+        let ih = sheet(&[
+            ".###.#..#",
+            "..#..#..#",
+            "..#..####",
+            "..#..#..#",
+            "..#..#..#",
+            ".###.#..#",
+        ]);
+        assert_eq!(ih.read(), Some(String::from("IH")));
+        assert_eq!(sheet(&["#", "#", "#", "#", "#"]).read(), None);
+        assert_eq!(sheet(&["####", "#..#", "#..#", "#..#", "#..#", "####"]).read(), None);
+    }
+
+    #[test]
+    fn folds() {
+        assert_eq!(Fold::parse("fold along x=5"), Some(Fold::X(5)));
+        assert_eq!(Fold::parse("fold along x+y=-3"), Some(Fold::AntiDiagonal(-3)));
+        assert_eq!(Fold::parse("fold along z=1"), None);
+
+        // Off-centre: the longer part hangs past the edge
+        let mut strip = Sheet::new([(0, 0), (1, 0), (5, 0)]);
+        strip.fold(Fold::X(1));
+        assert_eq!(strip, Sheet::new([(0, 0), (1, 0), (-3, 0)]));
+
+        let mut corner = Sheet::new([(3, 0), (0, 3), (2, 2)]);
+        corner.fold(Fold::Diagonal(0));
+        assert_eq!(corner, Sheet::new([(0, 3), (2, 2)]));
+        corner.fold(Fold::AntiDiagonal(3));
+        assert_eq!(corner, Sheet::new([(0, 3), (1, 1)]));
+    }
+}
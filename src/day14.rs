@@ -1,47 +1,194 @@
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
+
+use num_traits::{CheckedAdd, CheckedMul};
+
+use crate::matrix::{mat_pow, Matrix};
 
 type Pair = (char, char);
 
+/// A polymer template and its pair insertion rules. Pairs without a rule are left alone.
+pub struct Polymer {
+    template: Vec<char>,
+    rules: HashMap<Pair, char>,
+}
+
+impl Polymer {
+    /// The template on the first line, then one rule per line. Panics if there's no template, as an empty
+    /// polymer has no elements to count.
+    pub fn parse(input: &str) -> Polymer {
+        let mut lines = input.lines();
+        let template = lines.next()
+            .map(str::trim)
+            .filter(|template| !template.is_empty())
+            .expect("The first line should be the polymer template")
+            .chars()
+            .collect();
+        let rules = lines
+            .filter(|line| !line.trim().is_empty())
+            .map(|line| {
+                let (pair, insert) = line.split_once(" -> ").unwrap_or_else(|| panic!("Not a rule: {}", line));
+                let pair = pair.trim().chars().collect::<Vec<_>>();
+                let insert = insert.trim().chars().collect::<Vec<_>>();
+                match (&pair[..], &insert[..]) {
+                    (&[a, b], &[c]) => ((a, b), c),
+                    _ => panic!("Not a rule: {}", line),
+                }
+            })
+            .collect();
+        Polymer { template, rules }
+    }
 
-fn solve(input: String, iters: i32) -> usize {
-    let mut lines = input.lines();
-    let mut state: HashMap<Pair, usize> = HashMap::new();
-    for x in lines.next().unwrap().chars().collect::<Vec<char>>().windows(2) {
-        let pair = (*x.first().unwrap(), *x.last().unwrap());
-        *state.entry(pair).or_insert(0) += 1;
-    }
-    lines.next();
-    let mut rules: HashMap<Pair, Vec<Pair>> = HashMap::new();
-    for line in lines {
-        let mut chars = line.chars();
-        let a = chars.next().unwrap();
-        let c = chars.next().unwrap();
-        let b = chars.last().unwrap();
-        rules.insert((a, c), vec![(a, b), (b, c)]);
-    }
-
-    for _ in 0..iters {
-        let mut new_state: HashMap<Pair, usize> = HashMap::new();
-        for (pair, &count) in &state {
-            for &result in rules.get(pair).unwrap() {
-                *new_state.entry(result).or_insert(0) += count;
+    /// The polymer itself after `steps` steps. Its length roughly doubles every step, so this is only for
+    /// checking small cases.
+    pub fn expand(&self, steps: usize) -> String {
+        let mut polymer = self.template.clone();
+        for _ in 0..steps {
+            let mut next = Vec::with_capacity(polymer.len() * 2);
+            for (i, &element) in polymer.iter().enumerate() {
+                if let Some(&prev) = i.checked_sub(1).and_then(|i| polymer.get(i)) {
+                    next.extend(self.rules.get(&(prev, element)));
+                }
+                next.push(element);
             }
+            polymer = next;
         }
-        state = new_state;
+        polymer.into_iter().collect()
     }
 
-    let mut freqs: HashMap<char, usize> = HashMap::new();
-    freqs.insert(input.chars().next().unwrap(), 1);
-    for ((_, b), &count) in &state {
-        *freqs.entry(*b).or_insert(0) += count;
+    /// How many of each element there are after `steps` steps, leaving out those there are none of, or `None`
+    /// if a count doesn't fit in `T`.
+    ///
+    /// Only the count of each pair is tracked. Every element but the first is the second half of exactly one
+    /// pair, and the first never changes.
+    pub fn frequencies<T>(&self, steps: u64) -> Option<BTreeMap<char, T>>
+    where
+        T: Clone + From<u64> + CheckedAdd + CheckedMul + PartialEq,
+    {
+        // Every pair that can ever turn up, found from the template's by following the rules
+        let mut pairs = self.template.windows(2).map(|w| (w[0], w[1])).collect::<Vec<_>>();
+        pairs.sort();
+        pairs.dedup();
+        let mut index: HashMap<Pair, usize> = pairs.iter().enumerate().map(|(i, &p)| (p, i)).collect();
+        let mut k = 0;
+        while k < pairs.len() {
+            let (a, b) = pairs[k];
+            if let Some(&c) = self.rules.get(&(a, b)) {
+                for pair in [(a, c), (c, b)] {
+                    index.entry(pair).or_insert_with(|| {
+                        pairs.push(pair);
+                        pairs.len() - 1
+                    });
+                }
+            }
+            k += 1;
+        }
+
+        // m[to][from]: how many of pair `to` one pair `from` becomes in a step
+        let n = pairs.len();
+        let mut m: Matrix<u64> = vec![vec![0; n]; n];
+        for (from, &(a, b)) in pairs.iter().enumerate() {
+            match self.rules.get(&(a, b)) {
+                Some(&c) => {
+                    m[index[&(a, c)]][from] += 1;
+                    m[index[&(c, b)]][from] += 1;
+                }
+                None => m[from][from] += 1,
+            }
+        }
+        let m = mat_pow(m.into_iter().map(|row| row.into_iter().map(T::from).collect()).collect(), steps)?;
+
+        let mut counts = vec![0u64; n];
+        for w in self.template.windows(2) {
+            counts[index[&(w[0], w[1])]] += 1;
+        }
+        let mut frequencies = BTreeMap::new();
+        if let Some(&first) = self.template.first() {
+            frequencies.insert(first, T::from(1));
+        }
+        for (to, &(_, b)) in pairs.iter().enumerate() {
+            let count = m[to].iter()
+                .zip(&counts)
+                .try_fold(T::from(0), |total, (n, &count)| total.checked_add(&n.checked_mul(&T::from(count))?))?;
+            if count != T::from(0) {
+                let total = frequencies.entry(b).or_insert_with(|| T::from(0));
+                *total = total.checked_add(&count)?;
+            }
+        }
+        Some(frequencies)
     }
-    freqs.values().max().unwrap() - freqs.values().min().unwrap()
 }
 
-pub fn part1(input: String) {
-    println!("{}", solve(input, 10));
+fn solve(input: &str, steps: u64) -> u128 {
+    let frequencies = Polymer::parse(input).frequencies::<u128>(steps).expect("Counts don't fit in 128 bits");
+    frequencies.values().max().unwrap() - frequencies.values().min().unwrap()
+}
+
+pub fn part1(input: &str) -> String {
+    solve(input, 10).to_string()
+}
+
+pub fn part2(input: &str) -> String {
+    solve(input, 40).to_string()
 }
 
-pub fn part2(input: String) {
-    println!("{}", solve(input, 40));
-}
\ No newline at end of file
+#[cfg(test)]
+mod tests {
+    use num_bigint::BigUint;
+
+    use super::*;
+
+    const SAMPLE: &str = include_str!("../inputs/examples/day14_sample.txt");
+
+    fn count(polymer: &str) -> BTreeMap<char, u128> {
+        let mut counts = BTreeMap::new();
+        for ch in polymer.chars() {
+            *counts.entry(ch).or_insert(0) += 1;
+        }
+        counts
+    }
+
+    #[test]
+    fn expand() {
+        let polymer = Polymer::parse(SAMPLE);
+        assert_eq!(polymer.expand(0), "NNCB");
+        assert_eq!(polymer.expand(1), "NCNBCHB");
+        assert_eq!(polymer.expand(2), "NBCCNBBBCBHCB");
+        assert_eq!(polymer.expand(4), "NBBNBNBBCCNBCNCCNBBNBBNBBBNBBNBBCBHCBHHNHCBBCBHCB");
+    }
+
+    #[test]
+    fn frequencies_match_expansion() {
+        // No rule for the Xs, so they stay put
+        let inputs = [SAMPLE.to_string(), SAMPLE.replacen("NNCB", "NXXNCBX", 1), String::from("AA\n\nAA -> B")];
+        for input in &inputs {
+            let polymer = Polymer::parse(input);
+            for steps in 0..12 {
+                let expected = count(&polymer.expand(steps));
+                assert_eq!(polymer.frequencies::<u128>(steps as u64), Some(expected), "{} steps of {:?}", steps, input);
+            }
+        }
+    }
+
+    #[test]
+    fn large_step_counts() {
+        let polymer = Polymer::parse(SAMPLE);
+        assert_eq!(polymer.frequencies::<u128>(40).unwrap()[&'B'], 2192039569602);
+        assert_eq!(polymer.frequencies::<u128>(200), None);
+        let big = polymer.frequencies::<BigUint>(200).unwrap();
+        let total = big.values().sum::<BigUint>();
+        // Each step adds one element per pair, so the length goes from n to 2n - 1
+        assert_eq!(total, (BigUint::from(2u32).pow(200) * 3u32) + 1u32);
+    }
+
+    #[test]
+    #[should_panic(expected = "The first line should be the polymer template")]
+    fn missing_template() {
+        part1("\nCH -> B\nHH -> N");
+    }
+
+    #[test]
+    #[should_panic(expected = "The first line should be the polymer template")]
+    fn empty_input() {
+        part1("");
+    }
+}
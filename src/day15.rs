@@ -31,7 +31,7 @@ fn adjusted_risk(risk: usize, offset: usize) -> usize {
     new_risk
 }
 
-fn solve(input: String, tiles: usize) -> usize {
+fn solve(input: &str, tiles: usize) -> usize {
     let tile_width = input.lines().next().unwrap().len();
     let tile_height = input.lines().count();
     let mut risk_map: HashMap<Coord, usize> = HashMap::new();
@@ -83,10 +83,10 @@ fn solve(input: String, tiles: usize) -> usize {
     *risks.get(target).unwrap()
 }
 
-pub fn part1(input: String) {
-    println!("{}", solve(input, 1));
+pub fn part1(input: &str) -> String {
+    solve(input, 1).to_string()
 }
 
-pub fn part2(input: String) {
-    println!("{}", solve(input, 5));
+pub fn part2(input: &str) -> String {
+    solve(input, 5).to_string()
 }
\ No newline at end of file
@@ -147,8 +147,8 @@ impl Packet {
     }
 }
 
-fn string_to_bit_stream(s: String) -> Vec<u8> {
-    s.trim().chars().map(|c| match c {
+fn string_to_bit_stream(s: &str) -> Vec<u8> {
+    s.trim().chars().flat_map(|c| match c {
         '0' => vec![0, 0, 0, 0],
         '1' => vec![0, 0, 0, 1],
         '2' => vec![0, 0, 1, 0],
@@ -166,19 +166,19 @@ fn string_to_bit_stream(s: String) -> Vec<u8> {
         'E' => vec![1, 1, 1, 0],
         'F' => vec![1, 1, 1, 1],
         _ => unreachable!()
-    }).flatten().collect::<Vec<u8>>()
+    }).collect::<Vec<u8>>()
 }
 
-pub fn part1(input: String) {
+pub fn part1(input: &str) -> String {
     let bits = string_to_bit_stream(input);
     let packet = Packet::from_bitstream(&mut bits.iter());
-    println!("{}", packet.version_total());
+    packet.version_total().to_string()
 }
 
-pub fn part2(input: String) {
+pub fn part2(input: &str) -> String {
     let bits = string_to_bit_stream(input);
     let packet = Packet::from_bitstream(&mut bits.iter());
-    println!("{}", packet.get_value());
+    packet.get_value().to_string()
 }
 
 #[cfg(test)]
@@ -196,7 +196,7 @@ mod tests {
         ]);
 
         for (data, expected) in cases {
-            let bits = string_to_bit_stream(data);
+            let bits = string_to_bit_stream(&data);
             let packet = Packet::from_bitstream(&mut bits.iter());
             assert_eq!(packet.version_total(), expected);
         }
@@ -217,7 +217,7 @@ mod tests {
 
 
         for (data, expected) in cases {
-            let bits = string_to_bit_stream(data.clone());
+            let bits = string_to_bit_stream(&data);
             let packet = Packet::from_bitstream(&mut bits.iter());
             assert_eq!(packet.get_value(), expected);
         }
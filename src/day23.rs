@@ -1,5 +1,9 @@
 use std::collections::{HashMap, VecDeque};
 
+use tracing::{debug_span, trace};
+
+use crate::cancel::CancelToken;
+
 fn room(amph: char) -> usize {
     (amph as usize - 'A' as usize) * 2 + 3
 }
@@ -49,7 +53,7 @@ fn find_amphipods(grid: &Grid) -> Vec<Point> {
         .collect()
 }
 
-fn parse_input(input: String) -> Grid {
+fn parse_input(input: &str) -> Grid {
     input.lines().enumerate()
         .flat_map(|(row, line)| {
             line.chars().enumerate()
@@ -173,7 +177,7 @@ fn valid_destinations(grid: &Grid, row: Row, col: Col) -> Vec<(Point, PathLength
     dests
 }
 
-fn sort_layout(init: Grid) -> PathLength {
+fn sort_layout(init: Grid, cancel: &CancelToken) -> Option<PathLength> {
     let room_owners = "ABCD".chars()
         .map(|ch| (room(ch), ch))
         .collect::<HashMap<_, _>>();
@@ -189,17 +193,15 @@ fn sort_layout(init: Grid) -> PathLength {
 
     let mut states: HashMap<String, PathLength> = HashMap::new();
     let mut frontier: VecDeque<(Grid, PathLength)> = VecDeque::from([(init, 0usize)]);
+    let _span = debug_span!("sort_layout").entered();
     while !frontier.is_empty() {
+        if cancel.is_cancelled() { return None; }
         let (grid, path) = frontier.pop_front().unwrap();
         let grid_str = fmt_grid(&grid);
-        match states.get(&grid_str) {
-            Some(&score) => {
-                // If there's already a better score for this state, don't bother proceeding
-                if score <= path { continue; }
-            }
-            None => {}
-        }
+        // If there's already a better score for this state, don't bother proceeding
+        if states.get(&grid_str).is_some_and(|&score| score <= path) { continue; }
         states.insert(grid_str, path);
+        trace!(path, frontier = frontier.len(), seen = states.len(), "Expanding state");
         for src_pos in find_amphipods(&grid) {
             let atype = *grid.get(&src_pos).unwrap();
             for (dest, dist) in valid_destinations(&grid, src_pos.0, src_pos.1) {
@@ -221,15 +223,15 @@ fn sort_layout(init: Grid) -> PathLength {
             }
         }
     }
-    *states.get(&fmt_grid(&solved_state)).unwrap()
+    Some(*states.get(&fmt_grid(&solved_state)).unwrap())
 }
 
-pub fn part1(input: String) {
-    println!("{}", sort_layout(parse_input(input)));
+pub fn part1(input: &str, cancel: &CancelToken) -> String {
+    sort_layout(parse_input(input), cancel).map(|cost| cost.to_string()).unwrap_or_default()
 }
 
-pub fn part2(input: String) {
-    let extra_lines = vec![
+pub fn part2(input: &str, cancel: &CancelToken) -> String {
+    let extra_lines = [
         "  #D#C#B#A#",
         "  #D#B#A#C#",
     ];
@@ -238,27 +240,13 @@ pub fn part2(input: String) {
         lines.insert(3, line);
     }
     let layout = lines.join("\n");
-    println!("{}", sort_layout(parse_input(layout)));
+    sort_layout(parse_input(&layout), cancel).map(|cost| cost.to_string()).unwrap_or_default()
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
-    const SAMPLE: &str = "#############
-#...........#
-###B#C#B#D###
-  #A#D#C#A#
-  #########";
-
-    const EXTENDED_SAMPLE: &str = "#############
-#...........#
-###B#C#B#D###
-  #D#C#B#A#
-  #D#B#A#C#
-  #A#D#C#A#
-  #########";
-
     #[test]
     fn test_check_room() {
         let input = String::from(
@@ -270,7 +258,7 @@ mod tests {
   #.#B#B#D#
   #########"
         );
-        let grid = parse_input(input);
+        let grid = parse_input(&input);
 
         assert!(matches!(check_room(&grid, 'A'), RoomStatus::Ready));
         assert!(matches!(check_room(&grid, 'B'), RoomStatus::Ready));
@@ -286,7 +274,7 @@ mod tests {
   #A#.#C#A#
   #########";
 
-        let grid = parse_input(input.to_string());
+        let grid = parse_input(input);
 
         // Check that B in the A room can go into the hallway or home
         let walkable = walk(&grid, 2, 3);
@@ -339,12 +327,12 @@ mod tests {
     }
 
     #[test]
-    fn part1() {
-        assert_eq!(sort_layout(parse_input(String::from(SAMPLE))), 12521);
+    fn cancelled() {
+        let cancel = CancelToken::new();
+        cancel.cancel();
+        let sample = include_str!("../inputs/examples/day23_sample.txt");
+        assert_eq!(sort_layout(parse_input(sample), &cancel), None);
+        let (part1, _) = crate::get_day(23);
+        assert_eq!(part1.unwrap().run(sample, &cancel), "");
     }
-
-    #[test]
-    fn part2() {
-        assert_eq!(sort_layout(parse_input(String::from(EXTENDED_SAMPLE))), 44169);
-    }
-}
\ No newline at end of file
+}
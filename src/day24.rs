@@ -11,7 +11,7 @@ struct Stage {
     form: Form,
 }
 
-fn parse_input(input: &String) -> Vec<Stage> {
+fn parse_input(input: &str) -> Vec<Stage> {
     let num_stages = input.lines().count() / 18;
     let lines: Vec<_> = input.lines().collect();
     (0..num_stages)
@@ -42,8 +42,8 @@ struct Pair {
     diff: i64
 }
 
-fn get_pairs(input: String) -> Vec<Pair> {
-    let stages = parse_input(&input);
+fn get_pairs(input: &str) -> Vec<Pair> {
+    let stages = parse_input(input);
     let mut stack = vec![];
     let mut pairs = vec![];
     for (i, stage) in stages.iter().enumerate() {
@@ -58,7 +58,7 @@ fn get_pairs(input: String) -> Vec<Pair> {
     pairs
 }
 
-pub fn part1(input: String) {
+pub fn part1(input: &str) -> String {
     let pairs= get_pairs(input);
     let mut result = [0; 14];
     for pair in pairs.iter() {
@@ -70,10 +70,10 @@ pub fn part1(input: String) {
             result[pair.second] = 9 + pair.diff;
         }
     }
-    println!("{}", result.map(|x| x.to_string()).join(""));
+    result.map(|x| x.to_string()).join("")
 }
 
-pub fn part2(input: String) {
+pub fn part2(input: &str) -> String {
     let pairs= get_pairs(input);
     let mut result = [0; 14];
     for pair in pairs.iter() {
@@ -85,5 +85,5 @@ pub fn part2(input: String) {
             result[pair.second] = 1;
         }
     }
-    println!("{}", result.map(|x| x.to_string()).join(""));
+    result.map(|x| x.to_string()).join("")
 }
@@ -1,11 +1,10 @@
 use std::collections::HashMap;
 
-pub fn part1(input: String) {
-    println!("{}", solve(input));
-    println!();
+pub fn part1(input: &str) -> String {
+    solve(input).to_string()
 }
 
-fn parse_input(input: String) -> (usize, usize, HashMap<(usize, usize), char>) {
+fn parse_input(input: &str) -> (usize, usize, HashMap<(usize, usize), char>) {
     let nrows = input.lines().count();
     let ncols = input.lines().next().unwrap().chars().count();
     let cukes = input.lines().enumerate()
@@ -29,7 +28,7 @@ fn show_grid(nrows: usize, ncols: usize, grid: &HashMap<(usize, usize), char>) {
     println!();
 }
 
-fn solve(input: String) -> i32 {
+fn solve(input: &str) -> i32 {
     let (nrows, ncols, mut grid) = parse_input(input);
     let mut done = false;
     let to_right = |pos| {
@@ -77,23 +76,3 @@ fn solve(input: String) -> i32 {
     }
     iters
 }
-
-#[cfg(test)]
-mod tests {
-    use super::solve;
-
-    #[test]
-    fn sample() {
-        let input = "v...>>.vv>
-.vv>>.vv..
->>.>v>...v
->>v>>.>.v.
-v>v.vv.v..
->.>>..v...
-.vv..>.>v.
-v.v..>>v.v
-....v..v.>";
-
-        assert_eq!(solve(input.to_string()), 58)
-    }
-}
\ No newline at end of file
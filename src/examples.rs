@@ -0,0 +1,15 @@
+//! Tests generated from the fixtures in `inputs/examples`.
+//!
+//! Each `dayNN_NAME.txt` is an example input, and `dayNN_NAME.part1` / `dayNN_NAME.part2` hold the expected
+//! answers. Either answer file can be left out when an example only applies to one part.
+
+use crate::cancel::CancelToken;
+use crate::get_day;
+
+fn check(day: u32, part: usize, input: &str, expected: &str) {
+    let (part1, part2) = get_day(day);
+    let solver = [part1, part2][part - 1].expect("Day has no such part");
+    assert_eq!(solver.run(input, &CancelToken::new()).trim_end(), expected.trim_end());
+}
+
+include!(concat!(env!("OUT_DIR"), "/examples.rs"));
@@ -0,0 +1,842 @@
+use std::collections::{BTreeSet, HashSet};
+
+use crate::day11::{OctoGrid, Synchronisation};
+
+/// Small seedable PRNG (SplitMix64) so that generated inputs are reproducible across platforms and releases
+pub struct Rng {
+    state: u64,
+}
+
+impl Rng {
+    pub fn new(seed: u64) -> Rng {
+        Rng { state: seed }
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9E3779B97F4A7C15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58476D1CE4E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D049BB133111EB);
+        z ^ (z >> 31)
+    }
+
+    /// Uniform value in `0..n`
+    pub fn below(&mut self, n: usize) -> usize {
+        assert!(n > 0, "cannot pick below 0");
+        (self.next_u64() % n as u64) as usize
+    }
+
+    /// Uniform value in `lo..=hi`
+    pub fn range(&mut self, lo: i64, hi: i64) -> i64 {
+        assert!(lo <= hi, "empty range {}..={}", lo, hi);
+        lo + (self.next_u64() % (hi - lo + 1) as u64) as i64
+    }
+
+    pub fn chance(&mut self, p: f64) -> bool {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64 >= 1.0 - p
+    }
+
+    pub fn choose<'a, T>(&mut self, items: &'a [T]) -> &'a T {
+        &items[self.below(items.len())]
+    }
+
+    pub fn shuffle<T>(&mut self, items: &mut [T]) {
+        for i in (1..items.len()).rev() {
+            items.swap(i, self.below(i + 1));
+        }
+    }
+}
+
+pub type GenFn = fn(&mut Rng, usize) -> String;
+
+/// Get the input generator for a day along with a size comparable to the real puzzle input
+pub fn get_generator(day: u32) -> Option<(GenFn, usize)> {
+    let generator: (GenFn, usize) = match day {
+        1 => (day01, 2000),
+        2 => (day02, 1000),
+        3 => (day03, 1000),
+        4 => (day04, 100),
+        5 => (day05, 500),
+        6 => (day06, 300),
+        7 => (day07, 1000),
+        8 => (day08, 200),
+        9 => (day09, 100),
+        10 => (day10, 100),
+        11 => (day11, 10),
+        12 => (day12, 12),
+        13 => (day13, 900),
+        14 => (day14, 20),
+        15 => (day15, 100),
+        16 => (day16, 250),
+        17 => (day17, 100),
+        18 => (day18, 100),
+        19 => (day19, 30),
+        20 => (day20, 100),
+        21 => (day21, 0),
+        22 => (day22, 420),
+        23 => (day23, 2),
+        24 => (day24, 0),
+        25 => (day25, 137),
+        _ => return None,
+    };
+    Some(generator)
+}
+
+fn digit_grid(rng: &mut Rng, rows: usize, cols: usize, mut cell: impl FnMut(&mut Rng) -> u8) -> String {
+    (0..rows)
+        .map(|_| (0..cols).map(|_| (b'0' + cell(rng)) as char).collect::<String>())
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// `size` depth readings
+fn day01(rng: &mut Rng, size: usize) -> String {
+    let mut depth = rng.range(100, 200);
+    (0..size)
+        .map(|_| {
+            depth = (depth + rng.range(-10, 20)).clamp(100, 10000);
+            depth.to_string()
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// `size` commands, never rising above the surface
+fn day02(rng: &mut Rng, size: usize) -> String {
+    let mut depth = 0;
+    (0..size)
+        .map(|_| {
+            let x = rng.range(1, 9);
+            match rng.below(3) {
+                0 => format!("forward {}", x),
+                1 if depth >= x => {
+                    depth -= x;
+                    format!("up {}", x)
+                }
+                _ => {
+                    depth += x;
+                    format!("down {}", x)
+                }
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// `size` distinct report lines
+fn day03(rng: &mut Rng, size: usize) -> String {
+    let width = (usize::BITS - (2 * size).leading_zeros()).max(12) as usize;
+    let mut values = HashSet::new();
+    while values.len() < size {
+        values.insert(rng.next_u64() >> (64 - width));
+    }
+    // The CO2 rating keeps the least common bit, which empties the candidates if they all agree on a bit.
+    // Add a value that disagrees wherever that happens until both ratings can be found. Ties are left in,
+    // since the puzzle defines how each rating breaks them.
+    'search: loop {
+        for keep_common in [true, false] {
+            let mut candidates: Vec<u64> = values.iter().copied().collect();
+            for pos in (0..width).rev() {
+                if candidates.len() == 1 { break; }
+                let ones = candidates.iter().filter(|&&v| v >> pos & 1 == 1).count();
+                if ones == 0 || ones == candidates.len() {
+                    let prefix = candidates[0] >> pos ^ 1;
+                    let suffix = rng.next_u64() & ((1 << pos) - 1);
+                    values.insert(prefix << pos | suffix);
+                    continue 'search;
+                }
+                let common = (2 * ones >= candidates.len()) as u64;
+                candidates.retain(|&v| (v >> pos & 1 == common) == keep_common);
+            }
+        }
+        break;
+    }
+    let mut lines: Vec<String> = values.into_iter().map(|v| format!("{:0width$b}", v, width = width)).collect();
+    lines.sort();
+    rng.shuffle(&mut lines);
+    lines.join("\n")
+}
+
+/// `size` boards
+fn day04(rng: &mut Rng, size: usize) -> String {
+    let mut draws: Vec<usize> = (0..100).collect();
+    rng.shuffle(&mut draws);
+    let mut text = draws.iter().map(|n| n.to_string()).collect::<Vec<_>>().join(",");
+    for _ in 0..size {
+        let mut nums: Vec<usize> = (0..100).collect();
+        rng.shuffle(&mut nums);
+        text.push('\n');
+        for row in nums[..25].chunks(5) {
+            text.push('\n');
+            text.push_str(&row.iter().map(|n| format!("{:>2}", n)).collect::<Vec<_>>().join(" "));
+        }
+    }
+    text
+}
+
+/// `size` vent lines, a third each horizontal, vertical and diagonal
+fn day05(rng: &mut Rng, size: usize) -> String {
+    (0..size)
+        .map(|_| {
+            let (x1, y1) = (rng.range(10, 989), rng.range(10, 989));
+            let (dx, dy) = match rng.below(3) {
+                0 => (*rng.choose(&[-1, 1]), 0),
+                1 => (0, *rng.choose(&[-1, 1])),
+                _ => (*rng.choose(&[-1, 1]), *rng.choose(&[-1, 1])),
+            };
+            // Stay on the 0..1000 square
+            let room_x = if dx > 0 { 999 - x1 } else if dx < 0 { x1 } else { 999 };
+            let room_y = if dy > 0 { 999 - y1 } else if dy < 0 { y1 } else { 999 };
+            let len = rng.range(1, room_x.min(room_y).min(500));
+            format!("{},{} -> {},{}", x1, y1, x1 + dx * len, y1 + dy * len)
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// `size` lanternfish
+fn day06(rng: &mut Rng, size: usize) -> String {
+    (0..size).map(|_| rng.range(1, 5).to_string()).collect::<Vec<_>>().join(",")
+}
+
+/// `size` crabs
+fn day07(rng: &mut Rng, size: usize) -> String {
+    (0..size).map(|_| rng.below(2000).to_string()).collect::<Vec<_>>().join(",")
+}
+
+/// `size` display entries
+fn day08(rng: &mut Rng, size: usize) -> String {
+    const DIGITS: [&str; 10] = ["abcefg", "cf", "acdeg", "acdfg", "bcdf", "abdfg", "abdefg", "acf", "abcdefg", "abcdfg"];
+    fn scramble(rng: &mut Rng, wiring: &[char], digit: &str) -> String {
+        let mut wires: Vec<char> = digit.chars().map(|seg| wiring[(seg as u8 - b'a') as usize]).collect();
+        rng.shuffle(&mut wires);
+        wires.into_iter().collect()
+    }
+    (0..size)
+        .map(|_| {
+            let mut wiring: Vec<char> = "abcdefg".chars().collect();
+            rng.shuffle(&mut wiring);
+            let mut patterns: Vec<String> = DIGITS.iter().map(|d| scramble(rng, &wiring, d)).collect();
+            rng.shuffle(&mut patterns);
+            let outputs: Vec<String> = (0..4).map(|_| {
+                let digit = *rng.choose(&DIGITS);
+                scramble(rng, &wiring, digit)
+            }).collect();
+            format!("{} | {}", patterns.join(" "), outputs.join(" "))
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// `size`×`size` heightmap
+fn day09(rng: &mut Rng, size: usize) -> String {
+    digit_grid(rng, size, size, |rng| if rng.chance(0.3) { 9 } else { rng.below(9) as u8 })
+}
+
+/// `size` navigation lines with an odd number of incomplete ones
+fn day10(rng: &mut Rng, size: usize) -> String {
+    const PAIRS: [(char, char); 4] = [('(', ')'), ('[', ']'), ('{', '}'), ('<', '>')];
+    let mut incomplete = 0;
+    let mut lines: Vec<String> = (0..size)
+        .map(|_| {
+            let corrupt = rng.chance(0.5);
+            let corrupt_at = rng.range(10, 80) as usize;
+            let mut stack = vec![];
+            let mut line = String::new();
+            for step in 0..rng.range(80, 110) as usize {
+                // Keep the stack shallow enough for completion scores to fit in a u64
+                if stack.is_empty() || (stack.len() < 20 && rng.chance(0.55)) {
+                    let (open, close) = *rng.choose(&PAIRS);
+                    stack.push(close);
+                    line.push(open);
+                } else {
+                    let close = stack.pop().unwrap();
+                    if corrupt && step >= corrupt_at {
+                        line.push(PAIRS.iter().map(|p| p.1).find(|&c| c != close).unwrap());
+                        return line;
+                    }
+                    line.push(close);
+                }
+            }
+            // Lines meant to be corrupt can run out before reaching a closer, and any line can happen to close
+            // everything it opened
+            if !stack.is_empty() { incomplete += 1; }
+            line
+        })
+        .collect();
+    if incomplete % 2 == 0 {
+        lines.push(String::from("(["));
+    }
+    lines.join("\n")
+}
+
+/// `size`×`size` octopuses that all flash together eventually.
+///
+/// Uniformly random grids often never do, and the only way to tell is to run them, so the output is biased
+/// towards grids that sync. The first try is uniform, and each retry after it starts another tenth of the
+/// octopuses on one shared energy level. The eleventh has them all equal, which syncs as soon as anything
+/// flashes, so there are never more than ten runs of `OctoGrid::synchronise`. Bigger grids sync less often,
+/// so they lean further towards octopuses that start level.
+fn day11(rng: &mut Rng, size: usize) -> String {
+    for attempt in 0..10 {
+        let level = rng.below(10) as u8;
+        let shared = attempt as f64 / 10.0;
+        let grid = digit_grid(rng, size, size, |rng| if rng.chance(shared) { level } else { rng.below(10) as u8 });
+        if let Synchronisation::Synced(_) = OctoGrid::parse(&grid).synchronise() {
+            return grid;
+        }
+    }
+    let level = rng.below(10) as u8;
+    digit_grid(rng, size, size, |_| level)
+}
+
+/// `size` caves besides start and end, with no two big caves adjacent
+fn day12(rng: &mut Rng, size: usize) -> String {
+    let mut names: Vec<String> = vec![String::from("start"), String::from("end")];
+    let mut big = vec![false, false];
+    while names.len() < size.max(1) + 2 {
+        let is_big = rng.chance(0.25);
+        let base = if is_big { b'A' } else { b'a' };
+        let name: String = (0..2).map(|_| (base + rng.below(26) as u8) as char).collect();
+        if !names.contains(&name) {
+            names.push(name);
+            big.push(is_big);
+        }
+    }
+    let mut edges = BTreeSet::new();
+    let connect = |a: usize, b: usize, edges: &mut BTreeSet<(usize, usize)>| {
+        if a != b && a + b != 1 && !(big[a] && big[b]) {
+            edges.insert((a.min(b), a.max(b)));
+        }
+    };
+    // Spanning tree first so every cave is reachable, then some extra tunnels
+    for cave in 2..names.len() {
+        let mut other = rng.below(cave);
+        while other == 1 || big[cave] && big[other] {
+            other = rng.below(cave);
+        }
+        connect(other, cave, &mut edges);
+    }
+    connect(1, rng.range(2, names.len() as i64 - 1) as usize, &mut edges);
+    for _ in 0..size / 2 {
+        connect(rng.below(names.len()), rng.below(names.len()), &mut edges);
+    }
+    let mut lines: Vec<String> = edges.iter()
+        .map(|&(a, b)| if rng.chance(0.5) { (a, b) } else { (b, a) })
+        .map(|(a, b)| format!("{}-{}", names[a], names[b]))
+        .collect();
+    rng.shuffle(&mut lines);
+    lines.join("\n")
+}
+
+/// `size` dots folded down to a 40×6 display
+fn day13(rng: &mut Rng, size: usize) -> String {
+    let mut x_folds = vec![40];
+    let mut y_folds = vec![6];
+    while x_folds.len() < 5 { x_folds.push(2 * x_folds.last().unwrap() + 1); }
+    while y_folds.len() < 7 { y_folds.push(2 * y_folds.last().unwrap() + 1); }
+    // Light up a random pattern on the display and unfold its cells, mirroring across each fold line or not
+    let display: Vec<(i64, i64)> = (0..40).flat_map(|x| (0..6).map(move |y| (x, y))).collect();
+    let display: Vec<(i64, i64)> = display.into_iter().filter(|_| rng.chance(0.4)).collect();
+    let capacity = display.len() << (x_folds.len() + y_folds.len());
+    let mut dots = HashSet::new();
+    while dots.len() < size.min(capacity) {
+        let (mut x, mut y) = *rng.choose(&display);
+        for &fold in &x_folds { if rng.chance(0.5) { x = 2 * fold - x; } }
+        for &fold in &y_folds { if rng.chance(0.5) { y = 2 * fold - y; } }
+        dots.insert((x, y));
+    }
+    let mut lines: Vec<String> = dots.iter().map(|(x, y)| format!("{},{}", x, y)).collect();
+    lines.sort();
+    rng.shuffle(&mut lines);
+    let mut folds = vec![];
+    let (mut xs, mut ys) = (x_folds.iter().rev(), y_folds.iter().rev());
+    loop {
+        match (xs.next(), ys.next()) {
+            (None, None) => break,
+            (x, y) => {
+                if let Some(x) = x { folds.push(format!("fold along x={}", x)); }
+                if let Some(y) = y { folds.push(format!("fold along y={}", y)); }
+            }
+        }
+    }
+    format!("{}\n\n{}", lines.join("\n"), folds.join("\n"))
+}
+
+/// Template of length `size` over ten elements, with a rule for every pair
+fn day14(rng: &mut Rng, size: usize) -> String {
+    let mut alphabet: Vec<char> = ('A'..='Z').collect();
+    rng.shuffle(&mut alphabet);
+    alphabet.truncate(10);
+    let template: String = (0..size.max(2)).map(|_| *rng.choose(&alphabet)).collect();
+    let mut rules = vec![];
+    for &a in &alphabet {
+        for &b in &alphabet {
+            rules.push(format!("{}{} -> {}", a, b, rng.choose(&alphabet)));
+        }
+    }
+    rng.shuffle(&mut rules);
+    format!("{}\n\n{}", template, rules.join("\n"))
+}
+
+/// `size`×`size` risk grid
+fn day15(rng: &mut Rng, size: usize) -> String {
+    digit_grid(rng, size, size, |rng| rng.range(1, 9) as u8)
+}
+
+/// Transmission of about `size` packets
+fn day16(rng: &mut Rng, size: usize) -> String {
+    fn push_bits(bits: &mut Vec<u8>, value: u64, width: usize) {
+        for i in (0..width).rev() {
+            bits.push((value >> i & 1) as u8);
+        }
+    }
+
+    fn literal(rng: &mut Rng, bits: &mut Vec<u8>, max: u64) {
+        push_bits(bits, rng.below(8) as u64, 3);
+        push_bits(bits, 4, 3);
+        let value = rng.next_u64() % max;
+        let nibbles = (64 - value.leading_zeros() as usize).div_ceil(4).max(1);
+        for i in (0..nibbles).rev() {
+            push_bits(bits, (i > 0) as u64, 1);
+            push_bits(bits, value >> (4 * i) & 0xF, 4);
+        }
+    }
+
+    fn packet(rng: &mut Rng, bits: &mut Vec<u8>, budget: usize) {
+        if budget < 2 {
+            return literal(rng, bits, 1 << 20);
+        }
+        let mut children = vec![];
+        // Products only multiply two literals so that values stay well inside a u64
+        let op = if budget == 3 && rng.chance(0.3) {
+            1
+        } else if budget >= 3 && rng.chance(0.2) {
+            *rng.choose(&[5, 6, 7])
+        } else {
+            *rng.choose(&[0, 2, 3])
+        };
+        let num_children = match op {
+            1 | 5 | 6 | 7 => 2,
+            _ => rng.range(1, (budget as i64 - 1).min(5)) as usize,
+        };
+        let mut remaining = budget - 1;
+        for i in 0..num_children {
+            let share = if i + 1 == num_children {
+                remaining
+            } else {
+                rng.range(1, (remaining - (num_children - i - 1)) as i64) as usize
+            };
+            remaining -= share;
+            let mut child = vec![];
+            if op == 1 { literal(rng, &mut child, 1 << 16) } else { packet(rng, &mut child, share) }
+            children.push(child);
+        }
+        push_bits(bits, rng.below(8) as u64, 3);
+        push_bits(bits, op, 3);
+        let total: usize = children.iter().map(|c| c.len()).sum();
+        if total < 1 << 15 && rng.chance(0.5) {
+            push_bits(bits, 0, 1);
+            push_bits(bits, total as u64, 15);
+        } else {
+            push_bits(bits, 1, 1);
+            push_bits(bits, children.len() as u64, 11);
+        }
+        children.into_iter().for_each(|c| bits.extend(c));
+    }
+
+    let mut bits = vec![];
+    packet(rng, &mut bits, size.max(1));
+    bits.chunks(4)
+        .map(|nibble| {
+            let value = nibble.iter().chain([0, 0, 0].iter()).take(4).fold(0, |acc, &b| acc << 1 | b as u32);
+            char::from_digit(value, 16).unwrap().to_ascii_uppercase()
+        })
+        .collect()
+}
+
+/// Target area about `size` units away and below
+fn day17(rng: &mut Rng, size: usize) -> String {
+    let scale = size.max(10) as i64;
+    let x1 = rng.range(scale * 3 / 2, scale * 2);
+    // Wide enough to contain a triangular number, so some probe stalls above the target
+    let min_width = ((2 * x1) as f64).sqrt() as i64 + 2;
+    let x2 = x1 + rng.range(min_width, min_width + scale / 4);
+    let y1 = -rng.range(scale, scale * 3 / 2);
+    let y2 = y1 + rng.range(scale / 4, scale / 3);
+    format!("target area: x={}..{}, y={}..{}", x1, x2, y1, y2)
+}
+
+/// `size` snailfish numbers, mostly nested the full four pairs deep that a reduced number allows
+fn day18(rng: &mut Rng, size: usize) -> String {
+    fn number(rng: &mut Rng, depth: usize, out: &mut String) {
+        if depth == 0 || rng.chance(0.15) {
+            out.push_str(&rng.below(10).to_string());
+        } else {
+            out.push('[');
+            number(rng, depth - 1, out);
+            out.push(',');
+            number(rng, depth - 1, out);
+            out.push(']');
+        }
+    }
+
+    (0..size)
+        .map(|_| {
+            let mut line = String::from("[");
+            number(rng, 3, &mut line);
+            line.push(',');
+            number(rng, 3, &mut line);
+            line.push(']');
+            line
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// `size` scanners, each sharing at least 12 beacons with an earlier one
+fn day19(rng: &mut Rng, size: usize) -> String {
+    type Point = [i64; 3];
+    const RANGE: i64 = 1000;
+
+    fn sees(scanner: &Point, beacon: &Point) -> bool {
+        (0..3).all(|i| (beacon[i] - scanner[i]).abs() <= RANGE)
+    }
+
+    // All 24 proper rotations as signed axis permutations
+    let mut rotations = vec![];
+    for perm in [[0, 1, 2], [1, 2, 0], [2, 0, 1], [0, 2, 1], [2, 1, 0], [1, 0, 2]] {
+        let parity = if perm[0] == 0 && perm[1] == 1 || perm[0] == 1 && perm[1] == 2 || perm[0] == 2 && perm[1] == 0 { 1 } else { -1 };
+        for signs in 0..8 {
+            let sign: [i64; 3] = [0, 1, 2].map(|i| if signs >> i & 1 == 1 { -1 } else { 1 });
+            if parity * sign[0] * sign[1] * sign[2] == 1 {
+                rotations.push((perm, sign));
+            }
+        }
+    }
+
+    let mut scanners: Vec<Point> = vec![[0; 3]];
+    let mut beacons: Vec<Point> = vec![];
+    // Alignment divides by and matches on differences between beacons, so keep all of those
+    // nonzero and distinct in magnitude
+    fn place(rng: &mut Rng, lo: Point, hi: Point, beacons: &mut Vec<Point>) {
+        loop {
+            let b: Point = [0, 1, 2].map(|i| rng.range(lo[i], hi[i]));
+            let clashes = beacons.iter().any(|o| {
+                let d = [0, 1, 2].map(|i| (b[i] - o[i]).abs());
+                d.contains(&0) || d[0] == d[1] || d[1] == d[2] || d[0] == d[2]
+            });
+            if !clashes {
+                beacons.push(b);
+                return;
+            }
+        }
+    }
+    for _ in 0..8 {
+        place(rng, [-RANGE; 3], [RANGE; 3], &mut beacons);
+    }
+    while scanners.len() < size.max(1) {
+        let parent = *rng.choose(&scanners);
+        let pos: Point = [0, 1, 2].map(|i| parent[i] + rng.range(600, RANGE) * rng.choose(&[-1, 1]));
+        let lo: Point = [0, 1, 2].map(|i| parent[i].max(pos[i]) - RANGE);
+        let hi: Point = [0, 1, 2].map(|i| parent[i].min(pos[i]) + RANGE);
+        for _ in 0..12 {
+            place(rng, lo, hi, &mut beacons);
+        }
+        for _ in 0..8 {
+            place(rng, [0, 1, 2].map(|i| pos[i] - RANGE), [0, 1, 2].map(|i| pos[i] + RANGE), &mut beacons);
+        }
+        scanners.push(pos);
+    }
+
+    scanners.iter().enumerate()
+        .map(|(id, scanner)| {
+            let (perm, sign) = if id == 0 { ([0, 1, 2], [1; 3]) } else { *rng.choose(&rotations) };
+            let mut lines: Vec<String> = beacons.iter()
+                .filter(|b| sees(scanner, b))
+                .map(|b| {
+                    let rel: Point = [0, 1, 2].map(|i| b[perm[i]] - scanner[perm[i]]);
+                    format!("{},{},{}", rel[0] * sign[0], rel[1] * sign[1], rel[2] * sign[2])
+                })
+                .collect();
+            rng.shuffle(&mut lines);
+            format!("--- scanner {} ---\n{}", id, lines.join("\n"))
+        })
+        .collect::<Vec<_>>()
+        .join("\n\n")
+}
+
+/// Enhancement algorithm and a `size`×`size` image
+fn day20(rng: &mut Rng, size: usize) -> String {
+    let mut algorithm: Vec<char> = (0..512).map(|_| if rng.chance(0.5) { '#' } else { '.' }).collect();
+    // An infinite lit background has to go dark again on the next step
+    if algorithm[0] == '#' {
+        algorithm[511] = '.';
+    }
+    let image = (0..size)
+        .map(|_| (0..size).map(|_| if rng.chance(0.5) { '#' } else { '.' }).collect::<String>())
+        .collect::<Vec<_>>()
+        .join("\n");
+    format!("{}\n\n{}", algorithm.into_iter().collect::<String>(), image)
+}
+
+/// Two starting positions; `size` is ignored
+fn day21(rng: &mut Rng, _size: usize) -> String {
+    format!("Player 1 starting position: {}\nPlayer 2 starting position: {}", rng.range(1, 10), rng.range(1, 10))
+}
+
+/// `size` reboot steps, the first 20 inside the initialization region
+fn day22(rng: &mut Rng, size: usize) -> String {
+    (0..size)
+        .map(|i| {
+            let state = if i == 0 || rng.chance(0.7) { "on" } else { "off" };
+            let ranges: Vec<String> = ["x", "y", "z"].iter()
+                .map(|axis| {
+                    let (lo, len) = if i < 20 {
+                        (rng.range(-50, 40), rng.range(5, 50))
+                    } else {
+                        (rng.range(-100000, 80000), rng.range(10000, 50000))
+                    };
+                    let hi = if i < 20 { (lo + len).min(50) } else { lo + len };
+                    format!("{}={}..{}", axis, lo, hi)
+                })
+                .collect();
+            format!("{} {}", state, ranges.join(","))
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Burrow with rooms `size` deep.
+///
+/// Only the top two rows are shuffled, as in the puzzle, and any rows under them start out home. Part 2 slips
+/// two more rows in under the top one, so a third shuffled row would be buried four deep, and with seven places
+/// to wait in the hallway hardly any burrow like that can be sorted.
+///
+/// Some arrangements can't be sorted, either as they are or once part 2 unfolds them, as the amphipods block
+/// each other for good, so those are drawn again. So are the few that take too long to prove sortable.
+fn day23(rng: &mut Rng, size: usize) -> String {
+    const DOORS: [usize; 4] = [2, 4, 6, 8];
+    const STOPS: [usize; 7] = [0, 1, 3, 5, 7, 9, 10];
+    /// How many positions to look at before giving up on an arrangement
+    const BUDGET: usize = 20_000;
+
+    /// Whether every amphipod can get home, given each room's amphipods from the top down
+    fn sortable(rooms: [Vec<char>; 4]) -> bool {
+        let home = |ch: char| (ch as u8 - b'A') as usize;
+        let clear = |hall: &[char; 11], from: usize, to: usize| {
+            (from.min(to)..=from.max(to)).all(|i| i == from || hall[i] == '.')
+        };
+        let ready = |room: &[char], i: usize| room.iter().all(|&ch| ch == '.' || home(ch) == i);
+        let mut seen = HashSet::new();
+        let mut stack = vec![(['.'; 11], rooms)];
+        while let Some((mut hall, mut rooms)) = stack.pop() {
+            // Going home never gets in anyone's way, so send everyone who can straight there
+            loop {
+                let from_hall = (0..11).find(|&h| {
+                    hall[h] != '.' && ready(&rooms[home(hall[h])], home(hall[h])) && clear(&hall, h, DOORS[home(hall[h])])
+                });
+                let from_room = || (0..4).find_map(|i| {
+                    let top = rooms[i].iter().position(|&ch| ch != '.')?;
+                    let to = home(rooms[i][top]);
+                    (!ready(&rooms[i], i) && ready(&rooms[to], to) && clear(&hall, DOORS[i], DOORS[to]))
+                        .then_some((i, top))
+                });
+                let ch = if let Some(h) = from_hall {
+                    std::mem::replace(&mut hall[h], '.')
+                } else if let Some((i, top)) = from_room() {
+                    std::mem::replace(&mut rooms[i][top], '.')
+                } else {
+                    break;
+                };
+                let depth = rooms[home(ch)].iter().rposition(|&other| other == '.').unwrap();
+                rooms[home(ch)][depth] = ch;
+            }
+            if rooms.iter().enumerate().all(|(i, room)| room.iter().all(|&ch| ch != '.' && home(ch) == i)) {
+                return true;
+            }
+            // Two in the hallway that each have to get past the other never will
+            let stuck = (0..11).any(|a| (a + 1..11).any(|b| {
+                hall[a] != '.' && hall[b] != '.' && DOORS[home(hall[a])] > b && DOORS[home(hall[b])] < a
+            }));
+            if stuck || !seen.insert((hall, rooms.clone())) {
+                continue;
+            }
+            if seen.len() > BUDGET {
+                return false;
+            }
+            for (i, room) in rooms.iter().enumerate() {
+                if ready(room, i) {
+                    continue;
+                }
+                let depth = room.iter().position(|&ch| ch != '.').unwrap();
+                for &h in STOPS.iter().filter(|&&h| hall[h] == '.' && clear(&hall, h, DOORS[i])) {
+                    let (mut hall, mut rooms) = (hall, rooms.clone());
+                    hall[h] = room[depth];
+                    rooms[i][depth] = '.';
+                    stack.push((hall, rooms));
+                }
+            }
+        }
+        false
+    }
+
+    let depth = size.max(1);
+    let mut amphipods: Vec<char> = (0..depth).flat_map(|_| "ABCD".chars()).collect();
+    loop {
+        rng.shuffle(&mut amphipods[..4 * depth.min(2)]);
+        let folded = [0, 1, 2, 3].map(|i| amphipods.iter().skip(i).step_by(4).copied().collect::<Vec<_>>());
+        // Part 2 adds two rows under the top one
+        let unfolded: [Vec<char>; 4] = std::array::from_fn(|i| {
+            let mut room = folded[i].clone();
+            room.splice(1..1, [b"DCBA"[i] as char, b"DBAC"[i] as char]);
+            room
+        });
+        if sortable(folded) && sortable(unfolded) {
+            break;
+        }
+    }
+    let mut lines = vec![String::from("#############"), String::from("#...........#")];
+    for (row, room) in amphipods.chunks(4).enumerate() {
+        let (edge, end) = if row == 0 { ("###", "###") } else { ("  #", "#") };
+        lines.push(format!("{}{}#{}#{}#{}{}", edge, room[0], room[1], room[2], room[3], end));
+    }
+    lines.push(String::from("  #########"));
+    lines.join("\n")
+}
+
+/// MONAD program with 14 paired push/pop stages; `size` is ignored
+fn day24(rng: &mut Rng, _size: usize) -> String {
+    let mut stack = vec![];
+    let mut opens = 0;
+    let stages: Vec<(i64, i64, i64)> = (0..14)
+        .map(|_| {
+            if opens < 7 && (stack.is_empty() || rng.chance(0.5)) {
+                opens += 1;
+                let b = rng.range(1, 16);
+                stack.push(b);
+                (1, rng.range(10, 16), b)
+            } else {
+                let partner_b = stack.pop().unwrap();
+                // Keep the paired digits within 1..=9 of each other
+                let diff = rng.range(-8, partner_b.min(8));
+                (26, diff - partner_b, rng.range(1, 16))
+            }
+        })
+        .collect();
+    stages.iter()
+        .map(|(div, a, b)| {
+            format!("inp w\nmul x 0\nadd x z\nmod x 26\ndiv z {}\nadd x {}\neql x w\neql x 0\nmul y 0\nadd y 25\n\
+                     mul y x\nadd y 1\nmul z y\nmul y 0\nadd y w\nadd y {}\nmul y x\nadd z y", div, a, b)
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// `size`×`size` sea floor, a quarter each of east- and south-facing sea cucumbers, where the herds eventually
+/// stop moving.
+///
+/// Herds can also go round the sea floor forever, so a floor that gets back to an earlier position is drawn again.
+fn day25(rng: &mut Rng, size: usize) -> String {
+    /// Move every sea cucumber in `herd` that can, returning whether any did
+    fn step(floor: &mut [Vec<u8>], herd: u8) -> bool {
+        let (rows, cols) = (floor.len(), floor[0].len());
+        let ahead = |r: usize, c: usize| if herd == b'>' { (r, (c + 1) % cols) } else { ((r + 1) % rows, c) };
+        let moving: Vec<(usize, usize)> = (0..rows)
+            .flat_map(|r| (0..cols).map(move |c| (r, c)))
+            .filter(|&(r, c)| {
+                let (r2, c2) = ahead(r, c);
+                floor[r][c] == herd && floor[r2][c2] == b'.'
+            })
+            .collect();
+        for &(r, c) in &moving {
+            let (r2, c2) = ahead(r, c);
+            floor[r][c] = b'.';
+            floor[r2][c2] = herd;
+        }
+        !moving.is_empty()
+    }
+
+    loop {
+        let floor: Vec<Vec<u8>> = (0..size).map(|_| (0..size).map(|_| *rng.choose(b">v..")).collect()).collect();
+        let mut state = floor.clone();
+        let mut seen = HashSet::new();
+        while seen.insert(state.clone()) {
+            let moved_east = step(&mut state, b'>');
+            let moved_south = step(&mut state, b'v');
+            if !moved_east && !moved_south {
+                return floor.into_iter().map(|row| String::from_utf8(row).unwrap()).collect::<Vec<_>>().join("\n");
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::cancel::CancelToken;
+    use crate::day10::{Check, Language};
+    use crate::get_day;
+
+    use super::*;
+
+    #[test]
+    fn reproducible() {
+        for day in 1..=25 {
+            let (generator, size) = get_generator(day).unwrap();
+            let size = size.min(50);
+            assert_eq!(generator(&mut Rng::new(2021), size), generator(&mut Rng::new(2021), size), "day {}", day);
+        }
+    }
+
+    fn assert_solvable(day: u32, seed: u64, size: usize) {
+        let input = get_generator(day).unwrap().0(&mut Rng::new(seed), size);
+        let (part1, part2) = get_day(day);
+        for part in [part1, part2].into_iter().flatten() {
+            assert!(!part.run(&input, &CancelToken::new()).is_empty(), "day {} seed {}", day, seed);
+        }
+    }
+
+    /// Every day's solvers can answer what its generator makes. Inputs are a tenth of the usual size to keep this
+    /// quick, and days that ignore size only get one seed.
+    #[test]
+    fn solvable() {
+        for seed in [3, 4, 5, 42] {
+            for day in (1..=25).filter(|&day| day != 23) {
+                let size = get_generator(day).unwrap().1;
+                if size > 0 || seed == 42 {
+                    assert_solvable(day, seed, size / 10);
+                }
+            }
+        }
+    }
+
+    /// Random burrows take minutes to sort without optimisations
+    #[test]
+    #[cfg_attr(debug_assertions, ignore = "run with --release")]
+    fn day23_solvable() {
+        let size = get_generator(23).unwrap().1;
+        for seed in [3, 4, 5, 42] {
+            assert_solvable(23, seed, size);
+        }
+    }
+
+    #[test]
+    fn day23_depth() {
+        for size in 1..=6 {
+            // Two walls and the hallway, besides the rooms
+            assert_eq!(day23(&mut Rng::new(42), size).lines().count(), size + 3, "size {}", size);
+        }
+    }
+
+    #[test]
+    fn day10_median_exists() {
+        let language = Language::standard();
+        for seed in 0..20 {
+            let input = day10(&mut Rng::new(seed), 100);
+            let incomplete = input.lines()
+                .filter(|line| matches!(language.check(line), Check::Incomplete { .. }))
+                .count();
+            assert_eq!(incomplete % 2, 1, "seed {}", seed);
+        }
+    }
+}
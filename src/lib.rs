@@ -1,3 +1,13 @@
+use std::io::BufRead;
+
+use cancel::CancelToken;
+
+pub mod cancel;
+pub mod gen;
+mod matrix;
+#[cfg(test)]
+mod examples;
+
 // Days
 pub mod day01;
 pub mod day02;
@@ -25,40 +35,65 @@ pub mod day23;
 pub mod day24;
 pub mod day25;
 
-pub fn noop(_inp: String) {}
+/// The solver for one part of a day
+#[derive(Clone, Copy)]
+pub enum DayFn {
+    /// Takes the whole input at once
+    Text(fn(&str) -> String),
+    /// Reads the input line by line, so it never has to be held in memory all at once
+    Lines(fn(&mut dyn BufRead) -> String),
+    /// Takes the whole input at once, and gives up with an empty answer once the token is cancelled
+    Cancellable(fn(&str, &CancelToken) -> String),
+}
+
+impl DayFn {
+    /// Solve the part for an input that's already in memory. Only `Cancellable` parts look at `cancel`.
+    pub fn run(self, input: &str, cancel: &CancelToken) -> String {
+        match self {
+            DayFn::Text(solve) => solve(input),
+            DayFn::Lines(solve) => solve(&mut input.as_bytes()),
+            DayFn::Cancellable(solve) => solve(input, cancel),
+        }
+    }
+
+    pub fn is_cancellable(self) -> bool {
+        matches!(self, DayFn::Cancellable(_))
+    }
+}
 
-pub type DayFn = fn(String);
+pub fn get_day(day: u32) -> (Option<DayFn>, Option<DayFn>) {
+    use DayFn::{Cancellable, Lines, Text};
 
-pub fn get_day(day: u32) -> (DayFn, DayFn) {
-    return match day {
-        1 => (day01::part1, day01::part2),
-        2 => (day02::part1, day02::part2),
-        3 => (day03::part1, day03::part2),
-        4 => (day04::part1, day04::part2),
-        5 => (day05::part1, day05::part2),
-        6 => (day06::part1, day06::part2),
-        7 => (day07::part1, day07::part2),
-        8 => (day08::part1, day08::part2),
-        9 => (day09::part1, day09::part2),
-        10 => (day10::part1, day10::part2),
-        11 => (day11::part1, day11::part2),
-        12 => (day12::part1, day12::part2),
-        13 => (day13::part1, day13::part2),
-        14 => (day14::part1, day14::part2),
-        15 => (day15::part1, day15::part2),
-        16 => (day16::part1, day16::part2),
-        17 => (day17::part1, day17::part2),
-        18 => (day18::part1, day18::part2),
-        19 => (day19::part1, day19::part2),
-        20 => (day20::part1, day20::part2),
-        21 => (day21::part1, day21::part2),
-        22 => (day22::part1, day22::part2),
-        23 => (day23::part1, day23::part2),
-        24 => (day24::part1, day24::part2),
-        25 => (day25::part1, noop),
+    let (part1, part2) = match day {
+        1 => (Lines(day01::part1), Lines(day01::part2)),
+        2 => (Lines(day02::part1), Lines(day02::part2)),
+        3 => (Text(day03::part1), Text(day03::part2)),
+        4 => (Text(day04::part1), Text(day04::part2)),
+        5 => (Lines(day05::part1), Lines(day05::part2)),
+        6 => (Text(day06::part1), Text(day06::part2)),
+        7 => (Text(day07::part1), Text(day07::part2)),
+        8 => (Text(day08::part1), Text(day08::part2)),
+        9 => (Text(day09::part1), Text(day09::part2)),
+        10 => (Lines(day10::part1), Lines(day10::part2)),
+        11 => (Text(day11::part1), Text(day11::part2)),
+        12 => (Text(day12::part1), Text(day12::part2)),
+        13 => (Text(day13::part1), Text(day13::part2)),
+        14 => (Text(day14::part1), Text(day14::part2)),
+        15 => (Text(day15::part1), Text(day15::part2)),
+        16 => (Text(day16::part1), Text(day16::part2)),
+        17 => (Text(day17::part1), Text(day17::part2)),
+        18 => (Text(day18::part1), Text(day18::part2)),
+        19 => (Cancellable(day19::part1), Cancellable(day19::part2)),
+        20 => (Text(day20::part1), Text(day20::part2)),
+        21 => (Text(day21::part1), Text(day21::part2)),
+        22 => (Lines(day22::part1), Lines(day22::part2)),
+        23 => (Cancellable(day23::part1), Cancellable(day23::part2)),
+        24 => (Text(day24::part1), Text(day24::part2)),
+        25 => return (Some(Text(day25::part1)), None),
         _ => {
             println!("Unknown day: {}", day);
-            return (noop, noop);
+            return (None, None);
         },
     };
+    (Some(part1), Some(part2))
 }
\ No newline at end of file
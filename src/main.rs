@@ -1,19 +1,28 @@
 use std::env;
-use std::fs;
-use std::io;
-use std::time::{Instant, Duration};
+use std::fs::{self, File};
+use std::io::{self, BufReader};
+use std::panic;
+use std::sync::Arc;
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::thread;
+use std::time::{Instant, Duration, SystemTime, UNIX_EPOCH};
 
-use aoc2021::{get_day, noop};
+use tracing::{Span, info_span};
+use tracing_subscriber::EnvFilter;
+
+use aoc2021::{DayFn, get_day};
+use aoc2021::cancel::CancelToken;
+use aoc2021::gen::{self, Rng};
 
 fn fmt_time(ms: f64) -> String {
     if ms <= 1.0 {
         let micro_sec = ms * 1000.0;
-        return String::from(format!("{}µs", micro_sec.round()));
+        return format!("{}µs", micro_sec.round());
     }
     if ms < 1000.0 {
         let whole_ms = ms.floor();
         let rem_ms = ms - whole_ms;
-        return String::from(format!("{}ms ", whole_ms) + &fmt_time(rem_ms));
+        return format!("{}ms ", whole_ms) + &fmt_time(rem_ms);
     }
     let sec: f64 = ms / 1000.0;
     if sec < 60.0 {
@@ -22,21 +31,147 @@ fn fmt_time(ms: f64) -> String {
         return format!("{}s ", whole_sec) + &fmt_time(rem_ms);
     }
     let min: f64 = sec / 60.0;
-    return format!("{}m ", min.floor()) + &fmt_time((sec % 60.0) * 1000.0);
+    format!("{}m ", min.floor()) + &fmt_time((sec % 60.0) * 1000.0)
 }
 
 fn fmt_dur(dur: Duration) -> String {
-    return fmt_time(dur.as_secs_f64() * 1000.0);
+    fmt_time(dur.as_secs_f64() * 1000.0)
+}
+
+/// Log to stderr at a level set by the number of `-v` flags, falling back to `RUST_LOG` and then to warnings only
+fn init_tracing(verbosity: usize) {
+    let filter = match verbosity {
+        0 => EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new("warn")),
+        1 => EnvFilter::new("info"),
+        2 => EnvFilter::new("debug"),
+        _ => EnvFilter::new("trace"),
+    };
+    tracing_subscriber::fmt()
+        .with_env_filter(filter)
+        .with_writer(io::stderr)
+        .init();
+}
+
+enum Outcome {
+    Finished(String, Duration),
+    /// `abandoned` is set when the part couldn't be cancelled and is still running on its worker thread
+    TimedOut { abandoned: bool },
+}
+
+/// Run one part on a worker thread, giving up on it once `timeout` has passed.
+///
+/// A part that runs out of time has its cancellation token set. A cancellable part is waited for while it
+/// winds down, so it's finished before anything else starts; any other part is left running, as nothing can
+/// stop it.
+fn run_part(
+    part: impl FnOnce(&CancelToken) -> String + Send + 'static,
+    cancellable: bool,
+    timeout: Option<Duration>,
+) -> Outcome {
+    let token = CancelToken::new();
+    let worker_token = token.clone();
+    let (tx, rx) = mpsc::channel();
+    let span = Span::current();
+    let start = Instant::now();
+    let worker = thread::spawn(move || {
+        let _span = span.entered();
+        let answer = part(&worker_token);
+        // The runner may have stopped listening after a timeout
+        let _ = tx.send((answer, start.elapsed()));
+    });
+
+    let result = match timeout {
+        Some(limit) => rx.recv_timeout(limit),
+        None => rx.recv().map_err(|_| RecvTimeoutError::Disconnected),
+    };
+    match result {
+        Ok((answer, dur)) => Outcome::Finished(answer, dur),
+        Err(RecvTimeoutError::Timeout) => {
+            token.cancel();
+            if !cancellable {
+                return Outcome::TimedOut { abandoned: true };
+            }
+            if let Err(e) = worker.join() {
+                panic::resume_unwind(e);
+            }
+            Outcome::TimedOut { abandoned: false }
+        }
+        Err(RecvTimeoutError::Disconnected) => {
+            // The worker hung up without sending, so it panicked
+            match worker.join() {
+                Err(e) => panic::resume_unwind(e),
+                Ok(_) => unreachable!(),
+            }
+        }
+    }
+}
+
+/// `gen DAY [--size N] [--seed N]`: print a random input for a day
+fn generate(mut args: impl Iterator<Item = String>) {
+    let mut day = String::new();
+    let mut size = None;
+    let mut seed = None;
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--size" | "--seed" => {
+                let value = args.next().unwrap_or_default();
+                let Ok(n) = value.parse::<u64>() else {
+                    eprintln!("Invalid {}: {}", &arg[2..], value);
+                    return;
+                };
+                if arg == "--size" { size = Some(n as usize) } else { seed = Some(n) }
+            }
+            _ => day = arg,
+        }
+    }
+
+    let Some((generator, default_size)) = day.parse().ok().and_then(gen::get_generator) else {
+        eprintln!("Invalid day number: {}", day);
+        return;
+    };
+    let seed = seed.unwrap_or_else(|| {
+        SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_nanos() as u64
+    });
+    // Report the seed out of band so the input on stdout can be reproduced
+    eprintln!("Seed {}", seed);
+    println!("{}", generator(&mut Rng::new(seed), size.unwrap_or(default_size)));
 }
 
 fn main() {
-    // Get day string
-    let args: Vec<String> = env::args().collect();
+    let mut args = env::args().skip(1).peekable();
+    if args.peek().is_some_and(|arg| arg == "gen") {
+        args.next();
+        return generate(args);
+    }
+
     let mut day = String::new();
+    let mut timeout = None;
+    let mut verbosity = 0;
 
-    if args.len() >= 2 {
-        day = args[1].clone();
-    } else {
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--timeout" => {
+                let secs = args.next().unwrap_or_default();
+                match secs.parse::<f64>() {
+                    Ok(secs) if secs > 0.0 => timeout = Some(Duration::from_secs_f64(secs)),
+                    _ => {
+                        println!("Invalid timeout: {}", secs);
+                        return;
+                    }
+                }
+            }
+            "--verbose" => verbosity += 1,
+            flags if flags.len() > 1 && flags.strip_prefix('-').is_some_and(|vs| vs.chars().all(|c| c == 'v')) => {
+                verbosity += flags.len() - 1
+            }
+            _ => day = arg,
+        }
+    }
+    init_tracing(verbosity);
+
+    // Get day string
+    if day.is_empty() {
         println!("Enter day: ");
         io::stdin()
             .read_line(&mut day)
@@ -57,26 +192,39 @@ fn main() {
         .join("inputs")
         .join(format!("day{:02}.txt", day_num));
     println!("Reading {}", filename.display());
-    let input = fs::read_to_string(filename)
-        .expect("Error while reading");
+    // Only read the whole file in if some part needs it, and then only once
+    let mut text: Option<Arc<str>> = None;
 
     // Get corresponding function
+    let (part1, part2) = get_day(day_num);
 
-    let to_run = get_day(day_num);
     // Time it
-    if to_run.0 != noop {
-        println!("Running Part 1");
-        let part1_start = Instant::now();
-        to_run.0(input.clone());
-        let part1_dur = part1_start.elapsed();
-        println!("Took {}", fmt_dur(part1_dur));
+    for (name, part) in [("Part 1", part1), ("Part 2", part2)] {
+        let Some(part) = part else { continue };
+        println!("Running {}", name);
+        let _span = info_span!("part", day = day_num, part = name).entered();
+        let outcome = match part {
+            DayFn::Lines(solve) => {
+                let file = File::open(&filename).expect("Error while reading");
+                run_part(move |_| solve(&mut BufReader::new(file)), false, timeout)
+            }
+            _ => {
+                let text = text
+                    .get_or_insert_with(|| fs::read_to_string(&filename).expect("Error while reading").into())
+                    .clone();
+                run_part(move |cancel| part.run(&text, cancel), part.is_cancellable(), timeout)
+            }
+        };
+        match outcome {
+            Outcome::Finished(answer, dur) => {
+                println!("{}", answer);
+                println!("Took {}", fmt_dur(dur));
+            }
+            Outcome::TimedOut { abandoned: false } => println!("TIMEOUT after {}", fmt_dur(timeout.unwrap())),
+            Outcome::TimedOut { abandoned: true } => println!(
+                "TIMEOUT after {}; this part can't be cancelled, so it keeps running in the background",
+                fmt_dur(timeout.unwrap())
+            ),
+        }
     }
-
-    if to_run.1 != noop {
-        println!("Running Part 2");
-        let part2_start = Instant::now();
-        to_run.1(input.clone());
-        let part2_dur = part2_start.elapsed();
-        println!("Took {}", fmt_dur(part2_dur));
-    }
-}
\ No newline at end of file
+}
@@ -0,0 +1,45 @@
+//! Square matrices over any integer type that can report overflow
+
+use num_traits::{CheckedAdd, CheckedMul};
+
+pub type Matrix<T> = Vec<Vec<T>>;
+
+/// `a * b`, or `None` if any entry overflows
+fn mat_mul<T>(a: &Matrix<T>, b: &Matrix<T>) -> Option<Matrix<T>>
+where
+    T: Clone + From<u64> + CheckedAdd + CheckedMul,
+{
+    let n = a.len();
+    (0..n)
+        .map(|i| {
+            (0..n)
+                .map(|j| (0..n).try_fold(T::from(0), |sum, k| sum.checked_add(&a[i][k].checked_mul(&b[k][j])?)))
+                .collect()
+        })
+        .collect()
+}
+
+/// `base` to the power `exp` by repeated squaring, or `None` if any entry overflows.
+///
+/// When one step of a simulation is a linear map on a vector of counts, any number of steps is a single
+/// power of that map, so this takes about `2 log2(exp)` multiplications however many steps there are. Any
+/// integer type that can count exactly works, such as `u128` or `BigUint`.
+pub fn mat_pow<T>(mut base: Matrix<T>, mut exp: u64) -> Option<Matrix<T>>
+where
+    T: Clone + From<u64> + CheckedAdd + CheckedMul,
+{
+    let n = base.len();
+    let mut result = (0..n)
+        .map(|i| (0..n).map(|j| T::from((i == j) as u64)).collect())
+        .collect::<Matrix<T>>();
+    while exp > 0 {
+        if exp & 1 == 1 {
+            result = mat_mul(&result, &base)?;
+        }
+        exp >>= 1;
+        if exp > 0 {
+            base = mat_mul(&base, &base)?;
+        }
+    }
+    Some(result)
+}